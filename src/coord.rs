@@ -0,0 +1,195 @@
+//! Tile coordinates and their packed forms.
+//!
+//! The world is divided in regions of 64×64 tiles, themselves divided in chunks of 8×8
+//! tiles. The client loads an area of 104×104 tiles, 13×13 chunks, around the chunk of
+//! the local player; its bottom-left tile is the region base, from which local
+//! coordinates are counted.
+//!
+//! #Example
+//!
+//! ```
+//! #  use databuffer::*;
+//! let coord = Coordinate::new(3222, 3218, 0);
+//! assert_eq!(coord.region_id(), 12850);
+//! assert_eq!(coord.region_base(), Coordinate::new(3168, 3168, 0));
+//! assert_eq!(coord.local_to(coord.region_base()), Some((54, 50)));
+//!
+//! let mut buffer = DataBuffer::new();
+//! buffer.write_coord_packed(coord);
+//! assert_eq!(buffer.read_coord_packed(), coord);
+//! ```
+
+use {DataBuffer, Error, Result, Storage, StorageMut};
+
+/// The size of the area loaded by the client, in tiles
+const VIEW_SIZE: u16 = 104;
+
+/// A tile of the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Coordinate {
+    /// The x coordinate, from west to east
+    pub x: u16,
+    /// The y coordinate, from south to north
+    pub y: u16,
+    /// The height level, from 0 to 3
+    pub plane: u8
+}
+
+impl Coordinate {
+    /// Construct a coordinate
+    pub fn new(x: u16, y: u16, plane: u8) -> Coordinate {
+        Coordinate { x, y, plane }
+    }
+
+    /// Return the coordinate packed on 30 bits, as `plane << 28 | x << 14 | y`.
+    /// Only the 14 lowest bits of x and y and the 2 lowest bits of the plane are kept.
+    pub fn packed(&self) -> u32 {
+        (self.plane as u32 & 0x3) << 28 | (self.x as u32 & 0x3FFF) << 14 | (self.y as u32 & 0x3FFF)
+    }
+
+    /// Unpack a coordinate packed with `packed()`
+    pub fn from_packed(packed: u32) -> Coordinate {
+        Coordinate::new((packed >> 14 & 0x3FFF) as u16, (packed & 0x3FFF) as u16, (packed >> 28 & 0x3) as u8)
+    }
+
+    /// Return the x coordinate of the region, `x / 64`
+    pub fn region_x(&self) -> u16 {
+        self.x >> 6
+    }
+
+    /// Return the y coordinate of the region, `y / 64`
+    pub fn region_y(&self) -> u16 {
+        self.y >> 6
+    }
+
+    /// Return the id of the region, `region_x << 8 | region_y`, as used by the map archives
+    pub fn region_id(&self) -> u16 {
+        self.region_x() << 8 | self.region_y()
+    }
+
+    /// Return the x coordinate of the chunk, `x / 8`
+    pub fn chunk_x(&self) -> u16 {
+        self.x >> 3
+    }
+
+    /// Return the y coordinate of the chunk, `y / 8`
+    pub fn chunk_y(&self) -> u16 {
+        self.y >> 3
+    }
+
+    /// Return a key identifying the chunk and its plane, `plane << 24 | chunk_x << 12 | chunk_y`
+    pub fn chunk_hash(&self) -> u32 {
+        (self.plane as u32) << 24 | (self.chunk_x() as u32) << 12 | self.chunk_y() as u32
+    }
+
+    /// Return the offset of the tile in its region, from 0 to 63 on each axis
+    pub fn region_offset(&self) -> (u8, u8) {
+        ((self.x & 0x3F) as u8, (self.y & 0x3F) as u8)
+    }
+
+    /// Return the bottom-left tile of the area loaded by a client standing on this tile,
+    /// six chunks south-west of its chunk
+    pub fn region_base(&self) -> Coordinate {
+        Coordinate::new(self.chunk_x().saturating_sub(6) << 3, self.chunk_y().saturating_sub(6) << 3, self.plane)
+    }
+
+    /// Return the position of the tile relative to the given region base, as written in the
+    /// 7-bit fields of player updating, or `None` if the tile is outside the loaded area
+    pub fn local_to(&self, base: Coordinate) -> Option<(u8, u8)> {
+        let x = self.x.checked_sub(base.x).filter(|&x| x < VIEW_SIZE)?;
+        let y = self.y.checked_sub(base.y).filter(|&y| y < VIEW_SIZE)?;
+        Some((x as u8, y as u8))
+    }
+}
+
+impl<S: StorageMut> DataBuffer<S> {
+    /// Write a coordinate packed on 30 bits, as an unsigned 32-bit value.
+    /// The program crash if x or y does not fit in 14 bits, or the plane in 2 bits.
+    pub fn write_coord_packed(&mut self, coord: Coordinate) {
+        ::unwrap(self.try_write_coord_packed(coord))
+    }
+
+    /// Same as `write_coord_packed()`, but return an error if the coordinate cannot be packed.
+    /// Nothing is written on error.
+    pub fn try_write_coord_packed(&mut self, coord: Coordinate) -> Result<()> {
+        self.trace_write("write_coord_packed", &coord, |buf| {
+            if coord.x > 0x3FFF || coord.y > 0x3FFF {
+                return Err(Error::ValueOutOfRange { value: coord.x.max(coord.y) as u64, max: 0x3FFF });
+            }
+            if coord.plane > 3 {
+                return Err(Error::ValueOutOfRange { value: coord.plane as u64, max: 3 });
+            }
+            buf.try_write_u32(coord.packed())
+        })
+    }
+
+    /// Write the chunk of the given tile, the center of the area loaded by a client standing
+    /// on it, as its x and y coordinates. Revisions apply different transformations to
+    /// them, so each one is written with the given function.
+    ///
+    /// _Note_: This is the chunk of the tile, not the region base, which is six chunks
+    /// south-west of it.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::new();
+    /// // Revision 317 adds 128 to the low byte of x
+    /// buffer.write_center_chunk(Coordinate::new(3222, 3218, 0), |buf, x| {
+    ///     buf.try_write_u8((x >> 8) as u8)?;
+    ///     buf.try_write_u8_add(x as u8)
+    /// }, DataBuffer::try_write_u16); // buffer contains [0x01, 0x12, 0x01, 0x92]
+    /// ```
+    pub fn write_center_chunk<F, G>(&mut self, coord: Coordinate, write_x: F, write_y: G)
+        where F: FnOnce(&mut Self, u16) -> Result<()>, G: FnOnce(&mut Self, u16) -> Result<()>
+    {
+        ::unwrap(self.try_write_center_chunk(coord, write_x, write_y))
+    }
+
+    /// Same as `write_center_chunk()`, but return an error if a coordinate cannot be written.
+    /// Nothing is written on error.
+    pub fn try_write_center_chunk<F, G>(&mut self, coord: Coordinate, write_x: F, write_y: G) -> Result<()>
+        where F: FnOnce(&mut Self, u16) -> Result<()>, G: FnOnce(&mut Self, u16) -> Result<()>
+    {
+        self.trace_write("write_center_chunk", &coord, |buf| {
+            write_x(buf, coord.chunk_x())?;
+            write_y(buf, coord.chunk_y())
+        })
+    }
+}
+
+impl<S: Storage> DataBuffer<S> {
+    /// Read a coordinate packed on 30 bits, as an unsigned 32-bit value
+    pub fn read_coord_packed(&mut self) -> Coordinate {
+        ::unwrap(self.try_read_coord_packed())
+    }
+
+    /// Same as `read_coord_packed()`, but return an error if not enough bytes are available
+    pub fn try_read_coord_packed(&mut self) -> Result<Coordinate> {
+        self.trace_read("read_coord_packed", |buf| Ok(Coordinate::from_packed(buf.try_read_u32()?)))
+    }
+
+    /// Read a chunk written with `write_center_chunk()`, reading each coordinate with the
+    /// given function, and return its south-west tile on plane 0, since the plane is not sent
+    pub fn read_center_chunk<F, G>(&mut self, read_x: F, read_y: G) -> Coordinate
+        where F: FnOnce(&mut Self) -> Result<u16>, G: FnOnce(&mut Self) -> Result<u16>
+    {
+        ::unwrap(self.try_read_center_chunk(read_x, read_y))
+    }
+
+    /// Same as `read_center_chunk()`, but return an error if not enough bytes are available,
+    /// or if a chunk coordinate is out of the map. The cursor is left untouched on error.
+    pub fn try_read_center_chunk<F, G>(&mut self, read_x: F, read_y: G) -> Result<Coordinate>
+        where F: FnOnce(&mut Self) -> Result<u16>, G: FnOnce(&mut Self) -> Result<u16>
+    {
+        self.trace_read("read_center_chunk", |buf| buf.transaction(|buf| {
+            let chunk_x = read_x(buf)?;
+            let chunk_y = read_y(buf)?;
+            if chunk_x > 0x7FF || chunk_y > 0x7FF {
+                return Err(Error::ValueOutOfRange { value: chunk_x.max(chunk_y) as u64, max: 0x7FF });
+            }
+            Ok(Coordinate::new(chunk_x << 3, chunk_y << 3, 0))
+        }))
+    }
+}
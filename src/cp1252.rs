@@ -0,0 +1,218 @@
+//! The Windows-1252 charset, used by the RuneScape clients to encode strings.
+//!
+//! Bytes in the `0x00..0x80` and `0xA0..=0xFF` ranges map to the Unicode code points of
+//! the same value, while the `0x80..0xA0` range maps to typographic characters such as `'€'`.
+//! Five bytes of that range are not assigned to any character.
+
+use alloc::{string::String, vec::Vec};
+
+use {DataBuffer, Error, LengthKind, Result, Storage, StorageMut};
+
+/// The characters of the `0x80..0xA0` range, `None` for the unassigned bytes.
+const HIGH_CHARS: [Option<char>; 32] = [
+    Some('\u{20AC}'), None, Some('\u{201A}'), Some('\u{0192}'),
+    Some('\u{201E}'), Some('\u{2026}'), Some('\u{2020}'), Some('\u{2021}'),
+    Some('\u{02C6}'), Some('\u{2030}'), Some('\u{0160}'), Some('\u{2039}'),
+    Some('\u{0152}'), None, Some('\u{017D}'), None,
+    None, Some('\u{2018}'), Some('\u{2019}'), Some('\u{201C}'),
+    Some('\u{201D}'), Some('\u{2022}'), Some('\u{2013}'), Some('\u{2014}'),
+    Some('\u{02DC}'), Some('\u{2122}'), Some('\u{0161}'), Some('\u{203A}'),
+    Some('\u{0153}'), None, Some('\u{017E}'), Some('\u{0178}')
+];
+
+/// What to do with characters which cannot be encoded, or bytes which cannot be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unmappable {
+    /// Return an error.
+    Fail,
+    /// Encode unmappable characters as `'?'` and decode unassigned bytes as `U+FFFD`.
+    Replace
+}
+
+/// Decode a single byte, returning `None` if it is not assigned to any character.
+///
+/// #Example
+///
+/// ```
+/// # use databuffer::cp1252;
+/// assert_eq!(cp1252::decode(0x80), Some('€'));
+/// assert_eq!(cp1252::decode(0x81), None);
+/// ```
+pub fn decode(byte: u8) -> Option<char> {
+    match byte {
+        0x80..=0x9F => HIGH_CHARS[(byte - 0x80) as usize],
+        _ => Some(byte as char)
+    }
+}
+
+/// Encode a single character, returning `None` if the charset has no byte for it.
+pub fn encode(ch: char) -> Option<u8> {
+    match ch as u32 {
+        0x00..=0x7F | 0xA0..=0xFF => Some(ch as u8),
+        _ => HIGH_CHARS.iter().position(|&c| c == Some(ch)).map(|i| 0x80 + i as u8)
+    }
+}
+
+/// Encode a whole string. When `terminated` is true, NUL characters are treated as
+/// unmappable since they would end the string early.
+fn encode_str(val: &str, terminated: bool, policy: Unmappable) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(val.len());
+    for ch in val.chars() {
+        match encode(ch) {
+            Some(0) if terminated => {}
+            Some(byte) => {
+                bytes.push(byte);
+                continue;
+            }
+            None => {}
+        }
+
+        match policy {
+            Unmappable::Fail => return Err(Error::UnmappableChar { ch }),
+            Unmappable::Replace => bytes.push(b'?')
+        }
+    }
+    Ok(bytes)
+}
+
+/// Decode the given bytes, which start at `offset` in the buffer.
+fn decode_bytes(bytes: &[u8], offset: usize, policy: Unmappable) -> Result<String> {
+    let mut string = String::with_capacity(bytes.len());
+    for (i, &byte) in bytes.iter().enumerate() {
+        match (decode(byte), policy) {
+            (Some(ch), _) => string.push(ch),
+            (None, Unmappable::Replace) => string.push('\u{FFFD}'),
+            (None, Unmappable::Fail) => return Err(Error::UnmappableByte { offset: offset + i, byte })
+        }
+    }
+    Ok(string)
+}
+
+impl<S: StorageMut> DataBuffer<S> {
+    /// Write a null-terminated string encoded with the Windows-1252 charset.
+    /// The program crash if the string contains unmappable characters and the policy is `Unmappable::Fail`.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// # use databuffer::*;
+    /// let mut buffer = DataBuffer::new();
+    /// buffer.write_ntstr_cp1252("5€", Unmappable::Fail); // buffer contains [0x35, 0x80, 0x00]
+    /// ```
+    pub fn write_ntstr_cp1252(&mut self, val: &str, policy: Unmappable) {
+        ::unwrap(self.try_write_ntstr_cp1252(val, policy))
+    }
+
+    /// Same as `write_ntstr_cp1252()`, but return an error instead of crashing.
+    /// Nothing is written on error.
+    pub fn try_write_ntstr_cp1252(&mut self, val: &str, policy: Unmappable) -> Result<()> {
+        self.trace_write("write_ntstr_cp1252", val, |buf| {
+            let bytes = encode_str(val, true, policy)?;
+            buf.try_write_bytes(&bytes)?;
+            buf.try_write_u8(0)
+        })
+    }
+
+    /// Write a null-bookended string encoded with the Windows-1252 charset.
+    /// The program crash if the string contains unmappable characters and the policy is `Unmappable::Fail`.
+    pub fn write_dntstr_cp1252(&mut self, val: &str, policy: Unmappable) {
+        ::unwrap(self.try_write_dntstr_cp1252(val, policy))
+    }
+
+    /// Same as `write_dntstr_cp1252()`, but return an error instead of crashing.
+    /// Nothing is written on error.
+    pub fn try_write_dntstr_cp1252(&mut self, val: &str, policy: Unmappable) -> Result<()> {
+        self.trace_write("write_dntstr_cp1252", val, |buf| {
+            let bytes = encode_str(val, true, policy)?;
+            buf.try_write_u8(0)?;
+            buf.try_write_bytes(&bytes)?;
+            buf.try_write_u8(0)
+        })
+    }
+
+    /// Write a string encoded with the Windows-1252 charset, prefixed with its length.
+    /// The program crash if the string contains unmappable characters and the policy is
+    /// `Unmappable::Fail`, or if its length does not fit in the prefix.
+    pub fn write_str_cp1252(&mut self, val: &str, kind: LengthKind, policy: Unmappable) {
+        ::unwrap(self.try_write_str_cp1252(val, kind, policy))
+    }
+
+    /// Same as `write_str_cp1252()`, but return an error instead of crashing.
+    /// Nothing is written on error.
+    pub fn try_write_str_cp1252(&mut self, val: &str, kind: LengthKind, policy: Unmappable) -> Result<()> {
+        self.trace_write("write_str_cp1252", val, |buf| {
+            let bytes = encode_str(val, false, policy)?;
+            buf.try_write_length(kind, bytes.len())?;
+            buf.try_write_bytes(&bytes)
+        })
+    }
+}
+
+impl<S: Storage> DataBuffer<S> {
+    /// Read a null-terminated string encoded with the Windows-1252 charset.
+    /// The program crash if the terminator is missing, or if the string contains unassigned bytes
+    /// and the policy is `Unmappable::Fail`.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// # use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x35, 0x80, 0x00]);
+    /// assert_eq!(buffer.read_ntstr_cp1252(Unmappable::Fail), "5€");
+    /// ```
+    pub fn read_ntstr_cp1252(&mut self, policy: Unmappable) -> String {
+        ::unwrap(self.try_read_ntstr_cp1252(policy))
+    }
+
+    /// Same as `read_ntstr_cp1252()`, but return an error instead of crashing.
+    /// The cursor is left untouched on error.
+    pub fn try_read_ntstr_cp1252(&mut self, policy: Unmappable) -> Result<String> {
+        self.trace_read("read_ntstr_cp1252", |buf| buf.transaction(|buf| {
+            let offset = buf.remaining_start();
+            let mut bytes = Vec::new();
+            let mut current = buf.try_read_u8()?;
+            while current != 0 {
+                bytes.push(current);
+                current = buf.try_read_u8()?;
+            }
+            decode_bytes(&bytes, offset, policy)
+        }))
+    }
+
+    /// Read a null-bookended string encoded with the Windows-1252 charset.
+    /// The program crash if the string is not properly bookended, or if the string contains
+    /// unassigned bytes and the policy is `Unmappable::Fail`.
+    pub fn read_dntstr_cp1252(&mut self, policy: Unmappable) -> String {
+        ::unwrap(self.try_read_dntstr_cp1252(policy))
+    }
+
+    /// Same as `read_dntstr_cp1252()`, but return an error instead of crashing.
+    /// The cursor is left untouched on error.
+    pub fn try_read_dntstr_cp1252(&mut self, policy: Unmappable) -> Result<String> {
+        self.trace_read("read_dntstr_cp1252", |buf| buf.transaction(|buf| {
+            let offset = buf.remaining_start();
+            let found = buf.try_read_u8()?;
+            if found != 0 {
+                return Err(Error::UnexpectedByte { offset, expected: 0, found });
+            }
+            buf.try_read_ntstr_cp1252(policy)
+        }))
+    }
+
+    /// Read a string encoded with the Windows-1252 charset, prefixed with its length.
+    /// The program crash if not enough bytes are available, or if the string contains
+    /// unassigned bytes and the policy is `Unmappable::Fail`.
+    pub fn read_str_cp1252(&mut self, kind: LengthKind, policy: Unmappable) -> String {
+        ::unwrap(self.try_read_str_cp1252(kind, policy))
+    }
+
+    /// Same as `read_str_cp1252()`, but return an error instead of crashing.
+    /// The cursor is left untouched on error.
+    pub fn try_read_str_cp1252(&mut self, kind: LengthKind, policy: Unmappable) -> Result<String> {
+        self.trace_read("read_str_cp1252", |buf| buf.transaction(|buf| {
+            let size = buf.try_read_length(kind)?;
+            let offset = buf.remaining_start();
+            decode_bytes(buf.take(size)?, offset, policy)
+        }))
+    }
+}
@@ -0,0 +1,139 @@
+//! Human-readable dumps of the buffer content.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+
+use {DataBuffer, Storage};
+
+/// The number of bytes per line of the dumps.
+const LINE_WIDTH: usize = 16;
+
+impl<S: Storage> DataBuffer<S> {
+    /// Dump the buffer in the style of `xxd`: one line per 16 bytes, with the offset of the
+    /// line, the bytes in hexadecimal and their ASCII representation.
+    ///
+    /// The lines holding the reading or writing cursor are followed by a marker line, with
+    /// `R` under the byte of the reading cursor, `W` under the byte of the writing cursor,
+    /// and `*` when both cursors are on the same byte.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::new();
+    /// buffer.write_str("hello");
+    /// buffer.read_u32();
+    /// print!("{}", buffer.hexdump());
+    /// // 00000000: 00 00 00 05 68 65 6c 6c 6f                       ....hello
+    /// //                       R              W
+    /// ```
+    pub fn hexdump(&self) -> String {
+        let mut out = String::new();
+        let rpos = self.remaining_start();
+        let wpos = if self.wbit > 0 { self.wpos + 1 } else { self.wpos };
+        let lines = core::cmp::max(self.data.len(), core::cmp::max(rpos, wpos) + 1).div_ceil(LINE_WIDTH);
+
+        for line in 0..lines {
+            let start = line * LINE_WIDTH;
+            let end = core::cmp::min(start + LINE_WIDTH, self.data.len());
+            let bytes = if start < end { &self.data[start..end] } else { &[][..] };
+
+            let _ = write!(out, "{:08x}: ", start);
+            for i in 0..LINE_WIDTH {
+                match bytes.get(i) {
+                    Some(byte) => {
+                        let _ = write!(out, "{:02x} ", byte);
+                    }
+                    None => out.push_str("   ")
+                }
+            }
+            out.push(' ');
+            for &byte in bytes {
+                out.push(if (0x20..0x7F).contains(&byte) { byte as char } else { '.' });
+            }
+            out.push('\n');
+
+            let line_range = start..start + LINE_WIDTH;
+            if line_range.contains(&rpos) || line_range.contains(&wpos) {
+                let mut markers = " ".repeat(10);
+                for pos in line_range {
+                    markers.push_str(match (pos == rpos, pos == wpos) {
+                        (true, true) => "*  ",
+                        (true, false) => "R  ",
+                        (false, true) => "W  ",
+                        (false, false) => "   "
+                    });
+                }
+                out.push_str(markers.trim_end());
+                out.push('\n');
+            }
+        }
+
+        out
+    }
+
+    /// Render the buffer content as a Rust constant with the given name.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let buffer = DataBuffer::from_bytes(&vec![0x1, 0xFF]);
+    /// assert_eq!(buffer.to_rust_array("PACKET"), "const PACKET: [u8; 2] = [0x01, 0xff];\n");
+    /// ```
+    pub fn to_rust_array(&self, name: &str) -> String {
+        let items = self.array_items(|byte| format!("0x{:02x}", byte));
+        format!("const {}: [u8; {}] = [{}];\n", name, self.data.len(), items)
+    }
+
+    /// Render the buffer content as a C array with the given name.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let buffer = DataBuffer::from_bytes(&vec![0x1, 0xFF]);
+    /// assert_eq!(buffer.to_c_array("packet"), "const unsigned char packet[2] = {0x01, 0xff};\n");
+    /// ```
+    pub fn to_c_array(&self, name: &str) -> String {
+        let items = self.array_items(|byte| format!("0x{:02x}", byte));
+        format!("const unsigned char {}[{}] = {{{}}};\n", name, self.data.len(), items)
+    }
+
+    /// Render the buffer content as a Java `byte[]` initializer with the given name.
+    /// Bytes above `0x7f` are cast, since Java bytes are signed.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let buffer = DataBuffer::from_bytes(&vec![0x1, 0xFF]);
+    /// assert_eq!(buffer.to_java_array("packet"), "byte[] packet = {0x01, (byte) 0xff};\n");
+    /// ```
+    pub fn to_java_array(&self, name: &str) -> String {
+        let items = self.array_items(|byte| {
+            if byte > 0x7F {
+                format!("(byte) 0x{:02x}", byte)
+            } else {
+                format!("0x{:02x}", byte)
+            }
+        });
+        format!("byte[] {} = {{{}}};\n", name, items)
+    }
+
+    /// Join the formatted bytes with commas, on lines of 16 bytes if they do not fit on one.
+    fn array_items<F: Fn(u8) -> String>(&self, format: F) -> String {
+        let items: Vec<String> = self.data.iter().map(|&byte| format(byte)).collect();
+        if items.len() <= LINE_WIDTH {
+            return items.join(", ");
+        }
+
+        let mut out = String::from("\n");
+        for line in items.chunks(LINE_WIDTH) {
+            out.push_str("    ");
+            out.push_str(&line.join(", "));
+            out.push_str(",\n");
+        }
+        out
+    }
+}
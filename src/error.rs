@@ -0,0 +1,102 @@
+use alloc::string::String;
+use core::fmt;
+
+/// The errors reported by the fallible operations of a [`DataBuffer`](struct.DataBuffer.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A value is larger than the maximum the destination field can hold.
+    ValueOutOfRange { value: u64, max: u64 },
+    /// Not enough bytes are available to complete a read.
+    UnexpectedEof { needed: usize, remaining: usize },
+    /// A length prefix is larger than the maximum allowed by the caller.
+    LengthLimitExceeded { length: usize, max: usize },
+    /// A byte at the given offset does not have the expected value.
+    UnexpectedByte { offset: usize, expected: u8, found: u8 },
+    /// An absolute access of `size` bytes at the given offset falls outside the buffer.
+    OutOfBounds { offset: usize, size: usize, len: usize },
+    /// A variable-length integer starting at the given offset does not fit in its type.
+    VarIntOverflow { offset: usize },
+    /// A variable-length integer starting at the given offset uses more bytes than needed.
+    OverlongVarInt { offset: usize },
+    /// A character cannot be encoded in the requested charset.
+    UnmappableChar { ch: char },
+    /// A byte at the given offset is not assigned to any character in the requested charset.
+    UnmappableByte { offset: usize, byte: u8 },
+    /// The bytes of a string are not valid UTF-8, starting at the given offset.
+    InvalidUtf8 { offset: usize },
+    /// The bytes of a string are not valid Java modified UTF-8, starting at the given offset.
+    InvalidModifiedUtf8 { offset: usize },
+    /// A write needs the buffer to grow to `needed` bytes, past the capacity of its storage.
+    CapacityExceeded { needed: usize, capacity: usize },
+    /// The buffer needs to shrink to `needed` bytes, but its storage has a fixed length of `len`.
+    FixedLength { needed: usize, len: usize },
+    /// A value is not supported by the format it is written in, `what` describing it.
+    Unsupported { what: &'static str },
+    /// The write cursor was moved before the body of an open section, which starts at `body_start`.
+    SectionCursorMoved { body_start: usize, wpos: usize },
+    /// A custom error, such as one reported by a serde implementation.
+    Message(String),
+}
+
+/// A specialized result type for [`DataBuffer`](struct.DataBuffer.html) operations.
+pub type Result<T> = core::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::ValueOutOfRange { value, max } => {
+                write!(f, "value {} is out of range, the maximum is {}", value, max)
+            }
+            Error::UnexpectedEof { needed, remaining } => {
+                write!(f, "unexpected end of buffer, {} bytes needed but only {} remaining", needed, remaining)
+            }
+            Error::LengthLimitExceeded { length, max } => {
+                write!(f, "length {} exceeds the limit of {}", length, max)
+            }
+            Error::UnexpectedByte { offset, expected, found } => {
+                write!(f, "unexpected byte 0x{:02x} at offset {}, expected 0x{:02x}", found, offset, expected)
+            }
+            Error::OutOfBounds { offset, size, len } => {
+                write!(f, "cannot access {} bytes at offset {}, the buffer is only {} bytes long", size, offset, len)
+            }
+            Error::VarIntOverflow { offset } => {
+                write!(f, "variable-length integer at offset {} is too large", offset)
+            }
+            Error::OverlongVarInt { offset } => {
+                write!(f, "variable-length integer at offset {} has an overlong encoding", offset)
+            }
+            Error::UnmappableChar { ch } => {
+                write!(f, "character {:?} cannot be encoded in the requested charset", ch)
+            }
+            Error::UnmappableByte { offset, byte } => {
+                write!(f, "byte 0x{:02x} at offset {} is not assigned in the requested charset", byte, offset)
+            }
+            Error::InvalidUtf8 { offset } => {
+                write!(f, "invalid UTF-8 sequence at offset {}", offset)
+            }
+            Error::InvalidModifiedUtf8 { offset } => {
+                write!(f, "invalid modified UTF-8 sequence at offset {}", offset)
+            }
+            Error::CapacityExceeded { needed, capacity } => {
+                write!(f, "cannot grow the buffer to {} bytes, its capacity is {}", needed, capacity)
+            }
+            Error::FixedLength { needed, len } => {
+                write!(f, "cannot shrink the buffer to {} bytes, its length is fixed at {}", needed, len)
+            }
+            Error::Unsupported { what } => write!(f, "{} is not supported", what),
+            Error::SectionCursorMoved { body_start, wpos } => {
+                write!(f, "the write cursor was moved to {}, before the section body starting at {}", wpos, body_start)
+            }
+            Error::Message(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Without `std`, serde requires its own error trait, which is `core::error::Error` on the
+/// compilers that have it.
+#[cfg(all(not(feature = "std"), feature = "serde"))]
+impl serde::de::StdError for Error {}
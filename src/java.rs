@@ -0,0 +1,276 @@
+//! Compatibility with Java's `DataInputStream` and `DataOutputStream`.
+//!
+//! Java writes its primitives in big-endian order, like the default readers and writers of
+//! [`DataBuffer`](../struct.DataBuffer.html), and its strings in "modified UTF-8": a `u16`
+//! length followed by UTF-8 bytes, except that NUL is written as `0xC0 0x80` and characters
+//! outside the Basic Multilingual Plane are written as two encoded UTF-16 surrogates.
+
+use alloc::{string::String, vec::Vec};
+
+use {DataBuffer, Error, Result, Storage, StorageMut};
+
+/// Encode a string in modified UTF-8, without the length prefix.
+fn encode_modified_utf8(val: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(val.len());
+    for unit in val.encode_utf16() {
+        match unit {
+            0x0001..=0x007F => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07FF => {
+                bytes.push(0xC0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                bytes.push(0xE0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+    bytes
+}
+
+/// Decode modified UTF-8 bytes, which start at `offset` in the buffer.
+fn decode_modified_utf8(bytes: &[u8], offset: usize) -> Result<String> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let malformed = Error::InvalidModifiedUtf8 { offset: offset + i };
+        let continuation = |j: usize| match bytes.get(j) {
+            Some(&byte) if byte & 0xC0 == 0x80 => Ok((byte & 0x3F) as u16),
+            _ => Err(malformed.clone())
+        };
+
+        let byte = bytes[i];
+        match byte >> 4 {
+            0x0..=0x7 => {
+                units.push(byte as u16);
+                i += 1;
+            }
+            0xC | 0xD => {
+                units.push(((byte & 0x1F) as u16) << 6 | continuation(i + 1)?);
+                i += 2;
+            }
+            0xE => {
+                units.push(((byte & 0x0F) as u16) << 12 | continuation(i + 1)? << 6 | continuation(i + 2)?);
+                i += 3;
+            }
+            _ => return Err(malformed)
+        }
+    }
+
+    let mut string = String::with_capacity(units.len());
+    for ch in core::char::decode_utf16(units) {
+        // Java strings may hold unpaired surrogates, which a Rust string cannot represent
+        string.push(ch.map_err(|_| Error::InvalidModifiedUtf8 { offset })?);
+    }
+    Ok(string)
+}
+
+impl<S: StorageMut> DataBuffer<S> {
+    /// Write a string in the format of Java's `DataOutputStream.writeUTF()`: a `u16` length
+    /// followed by the string in modified UTF-8.
+    /// The program crash if the encoded string is longer than 65535 bytes.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// # use databuffer::*;
+    /// let mut buffer = DataBuffer::new();
+    /// buffer.write_java_utf("a\0"); // buffer contains [0x00, 0x03, 0x61, 0xC0, 0x80]
+    /// ```
+    pub fn write_java_utf(&mut self, val: &str) {
+        ::unwrap(self.try_write_java_utf(val))
+    }
+
+    /// Same as `write_java_utf()`, but return an error if the encoded string is longer than
+    /// 65535 bytes. Nothing is written on error.
+    pub fn try_write_java_utf(&mut self, val: &str) -> Result<()> {
+        self.trace_write("write_java_utf", val, |buf| {
+            let bytes = encode_modified_utf8(val);
+            if bytes.len() > u16::MAX as usize {
+                return Err(Error::ValueOutOfRange { value: bytes.len() as u64, max: u16::MAX as u64 });
+            }
+            buf.try_write_u16(bytes.len() as u16)?;
+            buf.try_write_bytes(&bytes)
+        })
+    }
+}
+
+impl<S: Storage> DataBuffer<S> {
+    /// Read a string written by Java's `DataOutputStream.writeUTF()`.
+    /// The program crash if not enough bytes are available or the string is malformed.
+    pub fn read_java_utf(&mut self) -> String {
+        ::unwrap(self.try_read_java_utf())
+    }
+
+    /// Same as `read_java_utf()`, but return an error instead of crashing.
+    /// The cursor is left untouched on error.
+    pub fn try_read_java_utf(&mut self) -> Result<String> {
+        self.trace_read("read_java_utf", |buf| buf.transaction(|buf| {
+            let size = buf.try_read_u16()? as usize;
+            let offset = buf.remaining_start();
+            decode_modified_utf8(buf.take(size)?, offset)
+        }))
+    }
+}
+
+/// The reading methods of Java's `DataInput` interface, with the same semantics.
+///
+/// Java throws an `EOFException` when not enough bytes are available, these methods
+/// return an error instead.
+pub trait DataInput {
+    /// Fill the given slice entirely, like `readFully()`
+    fn read_fully(&mut self, buf: &mut [u8]) -> Result<()>;
+    /// Skip up to n bytes and return the number of bytes skipped, like `skipBytes()`
+    fn skip_bytes(&mut self, n: usize) -> usize;
+    /// Read a byte and return true if it is not zero, like `readBoolean()`
+    fn read_boolean(&mut self) -> Result<bool>;
+    /// Like `readByte()`
+    fn read_byte(&mut self) -> Result<i8>;
+    /// Like `readUnsignedByte()`
+    fn read_unsigned_byte(&mut self) -> Result<u8>;
+    /// Like `readShort()`
+    fn read_short(&mut self) -> Result<i16>;
+    /// Like `readUnsignedShort()`
+    fn read_unsigned_short(&mut self) -> Result<u16>;
+    /// Read a UTF-16 code unit, like `readChar()`
+    fn read_char(&mut self) -> Result<u16>;
+    /// Like `readInt()`
+    fn read_int(&mut self) -> Result<i32>;
+    /// Like `readLong()`
+    fn read_long(&mut self) -> Result<i64>;
+    /// Like `readFloat()`
+    fn read_float(&mut self) -> Result<f32>;
+    /// Like `readDouble()`
+    fn read_double(&mut self) -> Result<f64>;
+    /// Like `readUTF()`
+    fn read_utf(&mut self) -> Result<String>;
+}
+
+/// The writing methods of Java's `DataOutput` interface, with the same semantics.
+pub trait DataOutput {
+    /// Write a byte set to 1 or 0, like `writeBoolean()`
+    fn write_boolean(&mut self, val: bool) -> Result<()>;
+    /// Like `writeByte()`
+    fn write_byte(&mut self, val: i8) -> Result<()>;
+    /// Like `writeShort()`
+    fn write_short(&mut self, val: i16) -> Result<()>;
+    /// Write a UTF-16 code unit, like `writeChar()`
+    fn write_char(&mut self, val: u16) -> Result<()>;
+    /// Like `writeInt()`
+    fn write_int(&mut self, val: i32) -> Result<()>;
+    /// Like `writeLong()`
+    fn write_long(&mut self, val: i64) -> Result<()>;
+    /// Like `writeFloat()`
+    fn write_float(&mut self, val: f32) -> Result<()>;
+    /// Like `writeDouble()`
+    fn write_double(&mut self, val: f64) -> Result<()>;
+    /// Write every UTF-16 code unit of the string, like `writeChars()`
+    fn write_chars(&mut self, val: &str) -> Result<()>;
+    /// Like `writeUTF()`
+    fn write_utf(&mut self, val: &str) -> Result<()>;
+}
+
+impl<S: Storage> DataInput for DataBuffer<S> {
+    fn read_fully(&mut self, buf: &mut [u8]) -> Result<()> {
+        buf.copy_from_slice(self.take(buf.len())?);
+        Ok(())
+    }
+
+    fn skip_bytes(&mut self, n: usize) -> usize {
+        let skipped = core::cmp::min(n, self.remaining());
+        self.skip(skipped);
+        skipped
+    }
+
+    fn read_boolean(&mut self) -> Result<bool> {
+        Ok(self.try_read_u8()? != 0)
+    }
+
+    fn read_byte(&mut self) -> Result<i8> {
+        self.try_read_i8()
+    }
+
+    fn read_unsigned_byte(&mut self) -> Result<u8> {
+        self.try_read_u8()
+    }
+
+    fn read_short(&mut self) -> Result<i16> {
+        self.try_read_i16()
+    }
+
+    fn read_unsigned_short(&mut self) -> Result<u16> {
+        self.try_read_u16()
+    }
+
+    fn read_char(&mut self) -> Result<u16> {
+        self.try_read_u16()
+    }
+
+    fn read_int(&mut self) -> Result<i32> {
+        self.try_read_i32()
+    }
+
+    fn read_long(&mut self) -> Result<i64> {
+        self.try_read_i64()
+    }
+
+    fn read_float(&mut self) -> Result<f32> {
+        self.try_read_f32()
+    }
+
+    fn read_double(&mut self) -> Result<f64> {
+        self.try_read_f64()
+    }
+
+    fn read_utf(&mut self) -> Result<String> {
+        self.try_read_java_utf()
+    }
+}
+
+impl<S: StorageMut> DataOutput for DataBuffer<S> {
+    fn write_boolean(&mut self, val: bool) -> Result<()> {
+        self.try_write_u8(val as u8)
+    }
+
+    fn write_byte(&mut self, val: i8) -> Result<()> {
+        self.try_write_i8(val)
+    }
+
+    fn write_short(&mut self, val: i16) -> Result<()> {
+        self.try_write_i16(val)
+    }
+
+    fn write_char(&mut self, val: u16) -> Result<()> {
+        self.try_write_u16(val)
+    }
+
+    fn write_int(&mut self, val: i32) -> Result<()> {
+        self.try_write_i32(val)
+    }
+
+    fn write_long(&mut self, val: i64) -> Result<()> {
+        self.try_write_i64(val)
+    }
+
+    fn write_float(&mut self, val: f32) -> Result<()> {
+        self.try_write_f32(val)
+    }
+
+    fn write_double(&mut self, val: f64) -> Result<()> {
+        self.try_write_f64(val)
+    }
+
+    fn write_chars(&mut self, val: &str) -> Result<()> {
+        self.trace_write("write_chars", val, |buf| {
+            for unit in val.encode_utf16() {
+                buf.try_write_u16(unit)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn write_utf(&mut self, val: &str) -> Result<()> {
+        self.try_write_java_utf(val)
+    }
+}
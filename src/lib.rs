@@ -1,19 +1,104 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 
 /// THIS FILE HAS BEEN MODIFIED HEAVILY BY Ceikry IN ORDER TO ACCOMODATE ADDITIONAL FEATURES, AND SEPARATE NAMING BASED ON PERSONAL PREFERENCE.
 /// Much love to the original authors who provided the groundwork <3 (Such authors are mentioned in the attributions in cargo.toml)
+#[cfg(feature = "std")]
+extern crate core;
 extern crate byteorder;
-
+#[cfg_attr(not(feature = "std"), macro_use)]
+extern crate alloc;
+#[cfg(feature = "serde")]
+extern crate serde;
+
+mod error;
+mod section;
+mod dump;
+mod trace;
+mod pool;
+pub mod storage;
+pub mod coord;
+pub mod cp1252;
+pub mod java;
+pub mod update;
+pub mod movement;
+pub mod palette;
+#[cfg(feature = "serde")]
+pub mod serialization;
+
+pub use error::{Error, Result};
+pub use section::{LengthKind, Section};
+pub use cp1252::Unmappable;
+pub use trace::TraceEntry;
+pub use pool::{DataBufferPool, PoolStats, PooledBuffer};
+pub use storage::{Fixed, Storage, StorageMut};
+pub use coord::Coordinate;
+
+use alloc::{borrow::ToOwned, boxed::Box, string::String, vec::Vec};
 use byteorder::{ByteOrder, BigEndian};
-use std::{io::{Read, Write, Result}, convert::TryInto};
-
-/// A data buffer object specifically turned to easily read and write binary values
-pub struct DataBuffer {
-    data: Vec<u8>,
+use core::convert::TryInto;
+#[cfg(feature = "std")]
+use std::io::{self, BufRead, IoSlice, Read, Seek, SeekFrom, Write};
+
+/// A data buffer object specifically turned to easily read and write binary values.
+///
+/// The bytes are held in a [`Storage`], a growable `Vec<u8>` by default. Reading works with
+/// any storage, such as a borrowed `&[u8]`, while writing needs a [`StorageMut`].
+/// See [`DataBuffer::fixed`] to write into caller-provided memory without allocating.
+pub struct DataBuffer<S = Vec<u8>> {
+    data: S,
     wpos: usize,
     rpos: usize,
     rbit: usize,
     wbit: usize,
-    header: PacketHeader
+    header: PacketHeader,
+    tracer: Option<Box<trace::Tracer>>,
+    undo: Option<Undo>
+}
+
+/// The state saved by the outermost write in progress, to undo it if it fails.
+/// Writes only move forward from `start`, so the overwritten bytes are contiguous.
+struct Undo {
+    start: usize,
+    len: usize,
+    saved: Vec<u8>
+}
+
+/// A range of bits reserved in a [`DataBuffer`] by [`DataBuffer::reserve_bits`],
+/// to be filled in later with [`DataBuffer::fill_bits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitReservation {
+    offset: usize,
+    bits: u8
+}
+
+impl BitReservation {
+    /// Return the index of the first reserved bit, counted from the start of the buffer
+    pub fn bit_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Return the number of reserved bits
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    /// Return the largest value that fits in the reserved bits
+    pub fn max_value(&self) -> u64 {
+        if self.bits >= 64 {
+            u64::MAX
+        } else {
+            (1 << self.bits) - 1
+        }
+    }
+}
+
+/// A snapshot of the cursors of a [`DataBuffer`], created by [`DataBuffer::mark`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mark {
+    rpos: usize,
+    rbit: usize,
+    wpos: usize,
+    wbit: usize
 }
 
 pub enum PacketHeader {
@@ -25,14 +110,7 @@ pub enum PacketHeader {
 impl DataBuffer {
     /// Construct a new, empty, DataBuffer
     pub fn new() -> DataBuffer {
-        DataBuffer {
-            data: vec![],
-            wpos: 0,
-            rpos: 0,
-            rbit: 0,
-            wbit: 0,
-            header: PacketHeader::NORMAL
-        }
+        DataBuffer::with_storage(vec![])
     }
 
     /// Borrows the data from a [`Vec<u8>`] to produce a [`DataBuffer`]. 
@@ -51,77 +129,283 @@ impl DataBuffer {
     /// the original vector anymore after calling this method.
     /// This method is useful to avoid excessive copying of data.
     pub fn with_vec(bytes: Vec<u8>) -> DataBuffer {
-        DataBuffer { data: bytes, wpos: 0, rpos: 0, rbit: 0, wbit: 0, header: PacketHeader::NORMAL }
+        DataBuffer::with_storage(bytes)
     }
 
     pub fn create(opcode: u8, header: PacketHeader) -> DataBuffer {
         let mut buff = DataBuffer::new();
-        buff.write_u8(opcode);
+        buff.start_packet(opcode, header);
+        buff
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> DataBuffer<Fixed<B>> {
+    /// Construct an empty buffer writing into the given memory, such as an inline `[u8; N]`
+    /// or a `&mut [u8]`, without ever allocating. Writing past the end of the memory is an
+    /// error, reported by the `try_write_*` functions, instead of growing the buffer.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::fixed([0u8; 4]);
+    /// buffer.write_u16(1);
+    /// assert_eq!(buffer.try_write_u32(2), Err(Error::CapacityExceeded { needed: 6, capacity: 4 }));
+    /// assert_eq!(buffer.to_bytes(), vec![0x00, 0x01]);
+    /// ```
+    pub fn fixed(bytes: B) -> DataBuffer<Fixed<B>> {
+        DataBuffer::with_storage(Fixed::new(bytes))
+    }
+}
+
+impl<S: Storage> DataBuffer<S> {
+    /// Construct a buffer over the given storage. Like with `with_vec()`, the content of
+    /// the storage is available for reading, and the writing cursor is at the start.
+    pub fn with_storage(storage: S) -> DataBuffer<S> {
+        DataBuffer {
+            data: storage,
+            wpos: 0,
+            rpos: 0,
+            rbit: 0,
+            wbit: 0,
+            header: PacketHeader::NORMAL,
+            tracer: None,
+            undo: None
+        }
+    }
+
+    /// Return the storage holding the bytes of the buffer
+    pub fn storage(&self) -> &S {
+        &self.data
+    }
+
+    /// Return the buffer size
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Return true if the buffer contains no data
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Return the number of bytes available for reading, from the reading cursor to the end of the buffer.
+    ///
+    /// *Note* : A partially read byte is not counted, since byte reads skip the pending bits.
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.remaining_start())
+    }
+
+    /// Return the position of the next byte a byte read would return,
+    /// skipping the partially read byte if any.
+    fn remaining_start(&self) -> usize {
+        if self.rbit > 0 { self.rpos + 1 } else { self.rpos }
+    }
+
+    /// Return true if at least n bytes are available for reading
+    pub fn has_remaining(&self, n: usize) -> bool {
+        self.remaining() >= n
+    }
+
+    /// Return the number of bytes the buffer can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+}
+
+impl<S: StorageMut> DataBuffer<S> {
+    /// Write the opcode and the size placeholder of a packet, see `create()`
+    fn start_packet(&mut self, opcode: u8, header: PacketHeader) {
+        self.write_u8(opcode);
 
         match header {
             PacketHeader::NORMAL => {},
             PacketHeader::BYTE => {
-                buff.write_u8(255);
+                self.write_u8(255);
             }
             PacketHeader::SHORT => {
-                buff.write_u16(65535);
+                self.write_u16(65535);
             }
         }
 
-        buff.header = header;
-
-        buff
+        self.header = header;
     }
 
     pub fn finish(&mut self){
-        let mut size = self.get_wpos();
-
-        self.set_wpos(1);
-        self.set_rpos(1);
-
         let header_bytes = match self.header {
             PacketHeader::NORMAL => 0,
             PacketHeader::BYTE => 1,
             PacketHeader::SHORT => 2
         };
 
-        size = ((size as u8) - header_bytes - 1).try_into().unwrap();
+        let size = self.get_wpos() - header_bytes - 1;
 
         match self.header {
             PacketHeader::NORMAL => {},
             PacketHeader::BYTE => {
-                self.write_u8(size.try_into().unwrap());
+                self.put_u8_at(1, size.try_into().unwrap());
             }
             PacketHeader::SHORT => {
-                self.write_u16(size.try_into().unwrap());
+                self.put_u16_at(1, size.try_into().unwrap());
             }
         }
 
         self.set_rpos(0);
     }
 
-    /// Return the buffer size
-    pub fn len(&self) -> usize {
-        self.data.len()
+    /// Clear the buffer and reinitialize the reading and writing cursors, including
+    /// the pending bits, and the packet header.
+    /// The program crash if the storage has a fixed length, such as a `Box<[u8]>`.
+    pub fn clear(&mut self) {
+        unwrap(self.try_clear())
     }
 
-    /// Clear the buffer and reinitialize the reading and writing cursor
-    pub fn clear(&mut self) {
-        self.data.clear();
+    /// Same as `clear()`, but return an error if the storage cannot shrink.
+    /// The buffer is left untouched on error.
+    pub fn try_clear(&mut self) -> Result<()> {
+        self.data.try_resize(0)?;
         self.wpos = 0;
         self.rpos = 0;
+        self.wbit = 0;
+        self.rbit = 0;
+        self.header = PacketHeader::NORMAL;
+        Ok(())
+    }
+
+    /// Discard the bytes before the reading cursor and move the cursors back accordingly.
+    /// A partially read byte is kept. This is useful for long-lived buffers which are
+    /// continuously written to and read from.
+    /// The program crash if the storage has a fixed length, such as a `Box<[u8]>`.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x1, 0x2, 0x3]);
+    /// buffer.read_u16();
+    /// buffer.compact(); // buffer contains [0x3]
+    /// assert_eq!(buffer.len(), 1);
+    /// assert_eq!(buffer.read_u8(), 0x3);
+    /// ```
+    pub fn compact(&mut self) {
+        unwrap(self.try_compact())
+    }
+
+    /// Same as `compact()`, but return an error if the storage cannot shrink.
+    /// The buffer is left untouched on error.
+    pub fn try_compact(&mut self) -> Result<()> {
+        let discarded = self.rpos;
+        if discarded == 0 {
+            return Ok(());
+        }
+
+        let len = self.data.len();
+        self.data.bytes_mut().rotate_left(discarded);
+        if let Err(err) = self.data.try_resize(len - discarded) {
+            self.data.bytes_mut().rotate_right(discarded);
+            return Err(err);
+        }
+        self.rpos = 0;
+        if self.wpos >= discarded {
+            self.wpos -= discarded;
+        } else {
+            self.wpos = 0;
+            self.wbit = 0;
+        }
+        Ok(())
+    }
+
+    /// Begin a length-prefixed section at the writing cursor. The returned [`Section`]
+    /// can be written to like the buffer itself, and the length of everything written
+    /// to it is written in front of it, using the given encoding, once it is closed or dropped.
+    ///
+    /// For variable-length prefixes, the prefix is inserted when the section is closed,
+    /// shifting the section content.
+    ///
+    /// **Note** : Dropping a section whose length does not fit in the prefix makes the program
+    /// crash, use [`Section::close`] to handle this case.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::new();
+    /// {
+    ///     let mut outer = buffer.begin_section(LengthKind::U16);
+    ///     outer.write_u8(1);
+    ///     let mut inner = outer.begin_section(LengthKind::U8);
+    ///     inner.write_u16(2);
+    /// }
+    /// assert_eq!(buffer.to_bytes(), vec![0x00, 0x04, 0x01, 0x02, 0x00, 0x02]);
+    /// ```
+    pub fn begin_section(&mut self, kind: LengthKind) -> Section<'_, S> {
+        unwrap(self.try_begin_section(kind))
+    }
+
+    /// Same as `begin_section()`, but return an error if the buffer cannot hold
+    /// the placeholder of a fixed-size prefix.
+    pub fn try_begin_section(&mut self, kind: LengthKind) -> Result<Section<'_, S>> {
+        Section::new(self, kind)
     }
 
     /// Change the buffer size to size.
     ///
     /// _Note_: You cannot shrink a buffer with this method
     pub fn resize(&mut self, size: usize) {
-        let diff = size - self.data.len();
-        if diff > 0 {
-            self.data.extend(std::iter::repeat(0).take(diff))
+        unwrap(self.try_resize(size))
+    }
+
+    /// Same as `resize()`, but return an error if the storage cannot hold `size` bytes
+    pub fn try_resize(&mut self, size: usize) -> Result<()> {
+        if size > self.data.len() {
+            self.data.try_resize(size)?;
+        }
+        Ok(())
+    }
+
+    /// Shrink the content of the storage back to `len` bytes, the length it had before
+    /// a write, which never fails
+    fn truncate(&mut self, len: usize) {
+        unwrap(self.data.try_resize(len))
+    }
+
+    /// Run the given write on this buffer, dropping the bytes it appended, restoring the
+    /// bytes it overwrote and restoring the cursors if it returns an error.
+    /// Writes nested in another one are undone with it, so they skip the bookkeeping.
+    fn write_or_rollback<F: FnOnce(&mut Self) -> Result<()>>(&mut self, f: F) -> Result<()> {
+        if self.undo.is_some() {
+            return f(self);
+        }
+
+        let mark = self.mark();
+        self.undo = Some(Undo { start: mark.wpos, len: self.data.len(), saved: Vec::new() });
+        let result = f(self);
+        if let (Err(_), Some(undo)) = (&result, self.undo.take()) {
+            self.truncate(undo.len);
+            self.data.bytes_mut()[undo.start..undo.start + undo.saved.len()].copy_from_slice(&undo.saved);
+            self.reset(mark);
+        }
+        self.undo = None;
+        result
+    }
+
+    /// Save the bytes before `end` that the write in progress is about to overwrite,
+    /// so they can be restored if it fails
+    fn save_overwritten(&mut self, end: usize) {
+        if let Some(ref mut undo) = self.undo {
+            let from = undo.start + undo.saved.len();
+            let to = core::cmp::min(end, undo.len);
+            if from < to {
+                undo.saved.extend_from_slice(&self.data[from..to]);
+            }
         }
     }
 
+    /// Reserve capacity for at least n more bytes, to avoid reallocating during writes
+    pub fn reserve(&mut self, n: usize) {
+        self.data.reserve(n);
+    }
+
     // Write operations
 
     /// Append a byte array to the buffer. The buffer is automatically extended if needed
@@ -134,18 +418,28 @@ impl DataBuffer {
     /// buffer.write_bytes(&vec![0x1, 0xFF, 0x45]); // buffer contains [0x1, 0xFF, 0x45]
     /// ```
     pub fn write_bytes(&mut self, bytes: &[u8]) {
-        self.flush_bit();
+        unwrap(self.try_write_bytes(bytes))
+    }
 
-        let size = bytes.len() + self.wpos;
+    /// Same as `write_bytes()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.trace_write("write_bytes", bytes, |buf| {
+            buf.flush_bit();
 
-        if size > self.data.len() {
-            self.resize(size);
-        }
+            let size = bytes.len() + buf.wpos;
 
-        for v in bytes {
-            self.data[self.wpos] = *v;
-            self.wpos += 1;
-        }
+            if size > buf.data.len() {
+                buf.try_resize(size)?;
+            }
+
+            buf.save_overwritten(size);
+            for v in bytes {
+                buf.data.bytes_mut()[buf.wpos] = *v;
+                buf.wpos += 1;
+            }
+            Ok(())
+        })
     }
 
     /// Append a byte (8 bits value) to the buffer
@@ -158,12 +452,24 @@ impl DataBuffer {
     /// buffer.write_u8(1) // buffer contains [0x1]
     /// ```
     pub fn write_u8(&mut self, val: u8) {
-        self.write_bytes(&[val]);
+        unwrap(self.try_write_u8(val))
+    }
+
+    /// Same as `write_u8()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u8(&mut self, val: u8) -> Result<()> {
+        self.trace_write("write_u8", &val, |buf| buf.try_write_bytes(&[val]))
     }
 
     /// Same as `write_u8()` but for signed values
     pub fn write_i8(&mut self, val: i8) {
-        self.write_u8(val as u8);
+        unwrap(self.try_write_i8(val))
+    }
+
+    /// Same as `write_i8()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_i8(&mut self, val: i8) -> Result<()> {
+        self.trace_write("write_i8", &val, |buf| buf.try_write_u8(val as u8))
     }
 
     /// Append a word (16 bits value) to the buffer
@@ -176,14 +482,28 @@ impl DataBuffer {
     /// buffer.write_u16(1) // buffer contains [0x00, 0x1] if little endian
     /// ```
     pub fn write_u16(&mut self, val: u16) {
-        let mut buf = [0; 2];
-        BigEndian::write_u16(&mut buf, val);
-        self.write_bytes(&buf);
+        unwrap(self.try_write_u16(val))
+    }
+
+    /// Same as `write_u16()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u16(&mut self, val: u16) -> Result<()> {
+        self.trace_write("write_u16", &val, |buf| {
+            let mut bytes = [0; 2];
+            BigEndian::write_u16(&mut bytes, val);
+            buf.try_write_bytes(&bytes)
+        })
     }
 
     /// Same as `write_u16()` but for signed values
     pub fn write_i16(&mut self, val: i16) {
-        self.write_u16(val as u16);
+        unwrap(self.try_write_i16(val))
+    }
+
+    /// Same as `write_i16()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_i16(&mut self, val: i16) -> Result<()> {
+        self.trace_write("write_i16", &val, |buf| buf.try_write_u16(val as u16))
     }
 
     /// Append a double word (32 bits value) to the buffer
@@ -196,14 +516,28 @@ impl DataBuffer {
     /// buffer.write_u32(1) // buffer contains [0x00, 0x00, 0x00, 0x1] if little endian
     /// ```
     pub fn write_u32(&mut self, val: u32) {
-        let mut buf = [0; 4];
-        BigEndian::write_u32(&mut buf, val);
-        self.write_bytes(&buf);
+        unwrap(self.try_write_u32(val))
+    }
+
+    /// Same as `write_u32()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u32(&mut self, val: u32) -> Result<()> {
+        self.trace_write("write_u32", &val, |buf| {
+            let mut bytes = [0; 4];
+            BigEndian::write_u32(&mut bytes, val);
+            buf.try_write_bytes(&bytes)
+        })
     }
 
     /// Same as `write_u32()` but for signed values
     pub fn write_i32(&mut self, val: i32) {
-        self.write_u32(val as u32);
+        unwrap(self.try_write_i32(val))
+    }
+
+    /// Same as `write_i32()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_i32(&mut self, val: i32) -> Result<()> {
+        self.trace_write("write_i32", &val, |buf| buf.try_write_u32(val as u32))
     }
 
     /// Append a quaddruple word (64 bits value) to the buffer
@@ -216,14 +550,28 @@ impl DataBuffer {
     /// buffer.write_u64(1) // buffer contains [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1] if little endian
     /// ```
     pub fn write_u64(&mut self, val: u64) {
-        let mut buf = [0; 8];
-        BigEndian::write_u64(&mut buf, val);
-        self.write_bytes(&buf);
+        unwrap(self.try_write_u64(val))
+    }
+
+    /// Same as `write_u64()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u64(&mut self, val: u64) -> Result<()> {
+        self.trace_write("write_u64", &val, |buf| {
+            let mut bytes = [0; 8];
+            BigEndian::write_u64(&mut bytes, val);
+            buf.try_write_bytes(&bytes)
+        })
     }
 
     /// Same as `write_u64()` but for signed values
     pub fn write_i64(&mut self, val: i64) {
-        self.write_u64(val as u64);
+        unwrap(self.try_write_i64(val))
+    }
+
+    /// Same as `write_i64()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_i64(&mut self, val: i64) -> Result<()> {
+        self.trace_write("write_i64", &val, |buf| buf.try_write_u64(val as u64))
     }
 
     /// Append a 32 bits floating point number to the buffer.
@@ -236,9 +584,17 @@ impl DataBuffer {
     /// buffer.write_f32(0.1)
     /// ```
     pub fn write_f32(&mut self, val: f32) {
-        let mut buf = [0; 4];
-        BigEndian::write_f32(&mut buf, val);
-        self.write_bytes(&buf);
+        unwrap(self.try_write_f32(val))
+    }
+
+    /// Same as `write_f32()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_f32(&mut self, val: f32) -> Result<()> {
+        self.trace_write("write_f32", &val, |buf| {
+            let mut bytes = [0; 4];
+            BigEndian::write_f32(&mut bytes, val);
+            buf.try_write_bytes(&bytes)
+        })
     }
 
     /// Append a 64 bits floating point number to the buffer.
@@ -251,9 +607,17 @@ impl DataBuffer {
     /// buffer.write_f64(0.1)
     /// ```
     pub fn write_f64(&mut self, val: f64) {
-        let mut buf = [0; 8];
-        BigEndian::write_f64(&mut buf, val);
-        self.write_bytes(&buf);
+        unwrap(self.try_write_f64(val))
+    }
+
+    /// Same as `write_f64()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_f64(&mut self, val: f64) -> Result<()> {
+        self.trace_write("write_f64", &val, |buf| {
+            let mut bytes = [0; 8];
+            BigEndian::write_f64(&mut bytes, val);
+            buf.try_write_bytes(&bytes)
+        })
     }
 
     /// Append a string to the buffer.
@@ -268,8 +632,16 @@ impl DataBuffer {
     /// buffer.write_str("Hello")
     /// ```
     pub fn write_str(&mut self, val: &str) {
-        self.write_u32(val.len() as u32);
-        self.write_bytes(val.as_bytes());
+        unwrap(self.try_write_str(val))
+    }
+
+    /// Same as `write_str()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_str(&mut self, val: &str) -> Result<()> {
+        self.trace_write("write_str", val, |buf| {
+            buf.try_write_u32(val.len() as u32)?;
+            buf.try_write_bytes(val.as_bytes())
+        })
     }
 
     /// Write a single null-terminated string to the buffer.
@@ -282,8 +654,16 @@ impl DataBuffer {
     /// buffer.write_ntstr("Hello");
     /// ```
     pub fn write_ntstr(&mut self, val: &str) {
-        self.write_bytes(val.as_bytes());
-        self.write_u8(0);
+        unwrap(self.try_write_ntstr(val))
+    }
+
+    /// Same as `write_ntstr()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_ntstr(&mut self, val: &str) -> Result<()> {
+        self.trace_write("write_ntstr", val, |buf| {
+            buf.try_write_bytes(val.as_bytes())?;
+            buf.try_write_u8(0)
+        })
     }
 
     /// Write a null-bookeneded string to the buffer.
@@ -295,377 +675,1209 @@ impl DataBuffer {
     /// buffer.write_dntstr("Hello");
     /// ```
     pub fn write_dntstr(&mut self, val: &str){
-        self.write_u8(0);
-        self.write_bytes(val.as_bytes());
-        self.write_u8(0);
+        unwrap(self.try_write_dntstr(val))
+    }
+
+    /// Same as `write_dntstr()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_dntstr(&mut self, val: &str) -> Result<()> {
+        self.trace_write("write_dntstr", val, |buf| {
+            buf.try_write_u8(0)?;
+            buf.try_write_bytes(val.as_bytes())?;
+            buf.try_write_u8(0)
+        })
     }
 
     /// Writes a smart to the buffer, which is a dynamically-sized unit with a max value of 32768.
+    /// The program crash if the value is larger than 32767.
     pub fn write_smart(&mut self, val: u16){
-        if val > 128 {
-            self.write_u16(val.wrapping_add(32768));
-        } else {
-            self.write_u8(val as u8);
-        }
+        unwrap(self.try_write_smart(val))
+    }
+
+    /// Same as `write_smart()`, but return an error if the value is larger than 32767 or
+    /// the buffer cannot hold it. Nothing is written on error.
+    pub fn try_write_smart(&mut self, val: u16) -> Result<()> {
+        self.trace_write("write_smart", &val, |buf| {
+            if val > 32767 {
+                return Err(Error::ValueOutOfRange { value: val as u64, max: 32767 });
+            }
+            if val >= 128 {
+                buf.try_write_u16(val.wrapping_add(32768))?;
+            } else {
+                buf.try_write_u8(val as u8)?;
+            }
+            Ok(())
+        })
     }
 
     /// Writes a medium to the buffer, which is a tribyte word.
     pub fn write_medium(&mut self, val: u32) {
-        self.write_i8((val >> 16) as i8);
-        self.write_i8(((val >> 8) & 0xFF) as i8);
-        self.write_i8((val & 0xFF) as i8);
+        unwrap(self.try_write_medium(val))
+    }
+
+    /// Same as `write_medium()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_medium(&mut self, val: u32) -> Result<()> {
+        self.trace_write("write_medium", &val, |buf| {
+            buf.try_write_i8((val >> 16) as i8)?;
+            buf.try_write_i8(((val >> 8) & 0xFF) as i8)?;
+            buf.try_write_i8((val & 0xFF) as i8)
+        })
     }
 
     /// Writes an inverted-signededness u8 to the buffer.
     pub fn write_u8_neg(&mut self, val: u8) {
-        self.write_i8(-(val as i8));    
+        unwrap(self.try_write_u8_neg(val))
+    }
+
+    /// Same as `write_u8_neg()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u8_neg(&mut self, val: u8) -> Result<()> {
+        self.trace_write("write_u8_neg", &val, |buf| buf.try_write_i8(-(val as i8)))
     }
 
     /// Writes an inverted-signededness i8 to the buffer.
     pub fn write_i8_neg(&mut self, val: i8) {
-        self.write_i8(val.wrapping_neg());    
+        unwrap(self.try_write_i8_neg(val))
+    }
+
+    /// Same as `write_i8_neg()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_i8_neg(&mut self, val: i8) -> Result<()> {
+        self.trace_write("write_i8_neg", &val, |buf| buf.try_write_i8(val.wrapping_neg()))
     }
 
     /// Writes a u8 + 128 to the buffer.
     pub fn write_u8_add(&mut self, val: u8) {
-        self.write_u8(val.wrapping_add(128));
+        unwrap(self.try_write_u8_add(val))
+    }
+
+    /// Same as `write_u8_add()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u8_add(&mut self, val: u8) -> Result<()> {
+        self.trace_write("write_u8_add", &val, |buf| buf.try_write_u8(val.wrapping_add(128)))
     }
 
     /// Writes a i8 + 128 to the buffer.
     pub fn write_i8_add(&mut self, val: i8) {
-        self.write_i8(val.wrapping_add(127).wrapping_add(1));
+        unwrap(self.try_write_i8_add(val))
+    }
+
+    /// Same as `write_i8_add()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_i8_add(&mut self, val: i8) -> Result<()> {
+        self.trace_write("write_i8_add", &val, |buf| buf.try_write_i8(val.wrapping_add(127).wrapping_add(1)))
     }
 
     /// Writes a u8 - 128 to the buffer.
     pub fn write_u8_sub(&mut self, val: u8) {
-        self.write_i8(val.wrapping_sub(128) as i8);
+        unwrap(self.try_write_u8_sub(val))
+    }
+
+    /// Same as `write_u8_sub()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u8_sub(&mut self, val: u8) -> Result<()> {
+        self.trace_write("write_u8_sub", &val, |buf| buf.try_write_i8(val.wrapping_sub(128) as i8))
     }
 
     /// Writes a i8 - 128 to the buffer.
     pub fn write_i8_sub(&mut self, val: i8) {
-        self.write_i8(val.wrapping_sub(127).wrapping_sub(1));
+        unwrap(self.try_write_i8_sub(val))
+    }
+
+    /// Same as `write_i8_sub()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_i8_sub(&mut self, val: i8) -> Result<()> {
+        self.trace_write("write_i8_sub", &val, |buf| buf.try_write_i8(val.wrapping_sub(127).wrapping_sub(1)))
     }
     
     /// Writes a little-endian u16 to the buffer.
     pub fn write_u16_le(&mut self, val: u16) {
-        self.write_u8(val as u8);
-        self.write_u8((val >> 8) as u8);
+        unwrap(self.try_write_u16_le(val))
+    }
+
+    /// Same as `write_u16_le()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u16_le(&mut self, val: u16) -> Result<()> {
+        self.trace_write("write_u16_le", &val, |buf| {
+            buf.try_write_u8(val as u8)?;
+            buf.try_write_u8((val >> 8) as u8)
+        })
     }
 
     /// Writes a little-endian i16 to the buffer.
     pub fn write_i16_le(&mut self, val: i16) {
-        self.write_i8(val as i8);
-        self.write_i8((val >> 8) as i8);
+        unwrap(self.try_write_i16_le(val))
+    }
+
+    /// Same as `write_i16_le()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_i16_le(&mut self, val: i16) -> Result<()> {
+        self.trace_write("write_i16_le", &val, |buf| {
+            buf.try_write_i8(val as i8)?;
+            buf.try_write_i8((val >> 8) as i8)
+        })
     }
 
     /// Writes a little-endian u16 + 128 to the buffer.
     pub fn write_u16_le_add(&mut self, val: u16) {
-        self.write_u8(val.wrapping_add(128) as u8);
-        self.write_u8((val >> 8) as u8);
+        unwrap(self.try_write_u16_le_add(val))
+    }
+
+    /// Same as `write_u16_le_add()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u16_le_add(&mut self, val: u16) -> Result<()> {
+        self.trace_write("write_u16_le_add", &val, |buf| {
+            buf.try_write_u8(val.wrapping_add(128) as u8)?;
+            buf.try_write_u8((val >> 8) as u8)
+        })
     }
 
     /// Writes a little-endian u16 + 128 to the buffer.
     pub fn write_i16_le_add(&mut self, val: i16) {
-        self.write_i8_add(val as i8);
-        self.write_i8((val >> 8) as i8);
+        unwrap(self.try_write_i16_le_add(val))
+    }
+
+    /// Same as `write_i16_le_add()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_i16_le_add(&mut self, val: i16) -> Result<()> {
+        self.trace_write("write_i16_le_add", &val, |buf| {
+            buf.try_write_i8_add(val as i8)?;
+            buf.try_write_i8((val >> 8) as i8)
+        })
     }
 
     /// Writes a little-endian u32 to the buffer.
     pub fn write_u32_le(&mut self, val: u32){
-        self.write_u8(val as u8);
-        self.write_u8((val >> 8) as u8);
-        self.write_u8((val >> 16) as u8);
-        self.write_u8((val >> 24) as u8);
+        unwrap(self.try_write_u32_le(val))
+    }
+
+    /// Same as `write_u32_le()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u32_le(&mut self, val: u32) -> Result<()> {
+        self.trace_write("write_u32_le", &val, |buf| {
+            buf.try_write_u8(val as u8)?;
+            buf.try_write_u8((val >> 8) as u8)?;
+            buf.try_write_u8((val >> 16) as u8)?;
+            buf.try_write_u8((val >> 24) as u8)
+        })
     }
 
     /// Writes a little-endian i32 to the buffer.
     pub fn write_i32_le(&mut self, val: i32){
-        self.write_i8(val as i8);
-        self.write_i8((val >> 8) as i8);
-        self.write_i8((val >> 16) as i8);
-        self.write_i8((val >> 24) as i8);
+        unwrap(self.try_write_i32_le(val))
+    }
+
+    /// Same as `write_i32_le()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_i32_le(&mut self, val: i32) -> Result<()> {
+        self.trace_write("write_i32_le", &val, |buf| {
+            buf.try_write_i8(val as i8)?;
+            buf.try_write_i8((val >> 8) as i8)?;
+            buf.try_write_i8((val >> 16) as i8)?;
+            buf.try_write_i8((val >> 24) as i8)
+        })
     }
 
     /// Writes a mixed-endian u32 to the buffer.
     pub fn write_u32_me(&mut self, val: u32){
-        self.write_u8((val >> 16) as u8);
-        self.write_u8((val >> 24) as u8);
-        self.write_u8(val as u8);
-        self.write_u8((val >> 8) as u8);
+        unwrap(self.try_write_u32_me(val))
+    }
+
+    /// Same as `write_u32_me()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u32_me(&mut self, val: u32) -> Result<()> {
+        self.trace_write("write_u32_me", &val, |buf| {
+            buf.try_write_u8((val >> 16) as u8)?;
+            buf.try_write_u8((val >> 24) as u8)?;
+            buf.try_write_u8(val as u8)?;
+            buf.try_write_u8((val >> 8) as u8)
+        })
     }
 
     /// Writes a reverse mixed-endian u32 to the buffer.
     pub fn write_u32_me_reversed(&mut self, val: u32){
-        self.write_u8((val >> 8) as u8);
-        self.write_u8(val as u8);
-        self.write_u8((val >> 24) as u8);
-        self.write_u8((val >> 16) as u8);
+        unwrap(self.try_write_u32_me_reversed(val))
     }
 
-    // Read operations
-
-    /// Read a defined amount of raw bytes. The program crash if not enough bytes are available
-    pub fn read_bytes(&mut self, size: usize) -> Vec<u8> {
-        self.flush_bit();
-        assert!(self.rpos + size <= self.data.len());
-        let range = self.rpos..self.rpos + size;
-        let mut res = Vec::<u8>::new();
-        res.write(&self.data[range]).unwrap();
-        self.rpos += size;
-        res
+    /// Same as `write_u32_me_reversed()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_u32_me_reversed(&mut self, val: u32) -> Result<()> {
+        self.trace_write("write_u32_me_reversed", &val, |buf| {
+            buf.try_write_u8((val >> 8) as u8)?;
+            buf.try_write_u8(val as u8)?;
+            buf.try_write_u8((val >> 24) as u8)?;
+            buf.try_write_u8((val >> 16) as u8)
+        })
     }
 
-    /// Read one byte. The program crash if not enough bytes are available
+    /// Writes an unsigned LEB128 variable-length integer to the buffer,
+    /// using 7 bits per byte with the high bit set on every byte but the last.
     ///
     /// #Example
     ///
     /// ```
     /// #  use databuffer::*;
-    /// let mut buffer = DataBuffer::from_bytes(&vec![0x1]);
-    /// let value = buffer.read_u8(); //Value contains 1
+    /// let mut buffer = DataBuffer::new();
+    /// buffer.write_var_u32(300); // buffer contains [0xAC, 0x02]
     /// ```
-    pub fn read_u8(&mut self) -> u8 {
-        self.flush_bit();
-        assert!(self.rpos < self.data.len());
-        let pos = self.rpos;
-        self.rpos += 1;
-        self.data[pos]
+    pub fn write_var_u32(&mut self, val: u32) {
+        unwrap(self.try_write_var_u32(val))
     }
 
-    /// Same as `read_u8()` but for signed values
-    pub fn read_i8(&mut self) -> i8 {
-        self.read_u8() as i8
+    /// Same as `write_var_u32()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_var_u32(&mut self, val: u32) -> Result<()> {
+        self.trace_write("write_var_u32", &val, |buf| buf.try_write_var_u64(val as u64))
     }
 
-    /// Read a 2-bytes long value. The program crash if not enough bytes are available
-    ///
-    /// #Example
-    ///
-    /// ```
-    /// #  use databuffer::*;
-    /// let mut buffer = DataBuffer::from_bytes(&vec![0x0, 0x1]);
-    /// let value = buffer.read_u16(); //Value contains 1
-    /// ```
-    pub fn read_u16(&mut self) -> u16 {
-        self.flush_bit();
-        assert!(self.rpos + 2 <= self.data.len());
-        let range = self.rpos..self.rpos + 2;
-        self.rpos += 2;
-        BigEndian::read_u16(&self.data[range])
+    /// Writes an unsigned LEB128 variable-length integer to the buffer.
+    pub fn write_var_u64(&mut self, val: u64) {
+        unwrap(self.try_write_var_u64(val))
     }
 
-    /// Same as `read_u16()` but for signed values
-    pub fn read_i16(&mut self) -> i16 {
-        self.read_u16() as i16
+    /// Same as `write_var_u64()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_var_u64(&mut self, val: u64) -> Result<()> {
+        self.trace_write("write_var_u64", &val, |buf| {
+            let mut bytes = [0; 10];
+            let size = encode_var_u64(val, &mut bytes);
+            buf.try_write_bytes(&bytes[..size])
+        })
     }
 
-    /// Read a four-bytes long value. The program crash if not enough bytes are available
+    /// Writes a zigzag-encoded LEB128 variable-length integer to the buffer,
+    /// so that small negative values are written with few bytes.
     ///
     /// #Example
     ///
     /// ```
     /// #  use databuffer::*;
-    /// let mut buffer = DataBuffer::from_bytes(&vec![0x0, 0x0, 0x0, 0x1]);
-    /// let value = buffer.read_u32(); // Value contains 1
+    /// let mut buffer = DataBuffer::new();
+    /// buffer.write_var_i32(-2); // buffer contains [0x03]
     /// ```
-    pub fn read_u32(&mut self) -> u32 {
-        self.flush_bit();
-        assert!(self.rpos + 4 <= self.data.len());
-        let range = self.rpos..self.rpos + 4;
-        self.rpos += 4;
-        BigEndian::read_u32(&self.data[range])
+    pub fn write_var_i32(&mut self, val: i32) {
+        unwrap(self.try_write_var_i32(val))
     }
 
-    /// Same as `read_u32()` but for signed values
-    pub fn read_i32(&mut self) -> i32 {
-        self.read_u32() as i32
+    /// Same as `write_var_i32()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_var_i32(&mut self, val: i32) -> Result<()> {
+        self.trace_write("write_var_i32", &val, |buf| buf.try_write_var_u32(((val << 1) ^ (val >> 31)) as u32))
     }
 
-    /// Read an eight bytes long value. The program crash if not enough bytes are available
+    /// Writes a zigzag-encoded LEB128 variable-length integer to the buffer.
+    pub fn write_var_i64(&mut self, val: i64) {
+        unwrap(self.try_write_var_i64(val))
+    }
+
+    /// Same as `write_var_i64()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_var_i64(&mut self, val: i64) -> Result<()> {
+        self.trace_write("write_var_i64", &val, |buf| buf.try_write_var_u64(((val << 1) ^ (val >> 63)) as u64))
+    }
+
+    /// Writes a length prefix with the given encoding.
+    /// The program crash if the length does not fit in the prefix.
+    pub fn write_length(&mut self, kind: LengthKind, length: usize) {
+        unwrap(self.try_write_length(kind, length))
+    }
+
+    /// Same as `write_length()`, but return an error if the length does not fit in the prefix
+    pub fn try_write_length(&mut self, kind: LengthKind, length: usize) -> Result<()> {
+        self.trace_write("write_length", &length, |buf| {
+            let mut encoded = [0; 10];
+            let size = kind.encode(length, &mut encoded)?;
+            buf.try_write_bytes(&encoded[..size])
+        })
+    }
+
+    /// Writes a byte array prefixed with its length, using the given encoding for the length.
+    /// The program crash if the length does not fit in the prefix.
     ///
     /// #Example
     ///
     /// ```
     /// #  use databuffer::*;
-    /// let mut buffer = DataBuffer::from_bytes(&vec![0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1]);
-    /// let value = buffer.read_u64(); //Value contains 1
+    /// let mut buffer = DataBuffer::new();
+    /// buffer.write_bytes_prefixed(&[0x1, 0x2], LengthKind::U16); // buffer contains [0x00, 0x02, 0x1, 0x2]
     /// ```
-    pub fn read_u64(&mut self) -> u64 {
-        self.flush_bit();
-        assert!(self.rpos + 8 <= self.data.len());
-        let range = self.rpos..self.rpos + 8;
-        self.rpos += 8;
-        BigEndian::read_u64(&self.data[range])
+    pub fn write_bytes_prefixed(&mut self, bytes: &[u8], kind: LengthKind) {
+        unwrap(self.try_write_bytes_prefixed(bytes, kind))
     }
 
-    /// Same as `read_u64()` but for signed values
-    pub fn read_i64(&mut self) -> i64 {
-        self.read_u64() as i64
+    /// Same as `write_bytes_prefixed()`, but return an error if the length does not fit
+    /// in the prefix. Nothing is written on error.
+    pub fn try_write_bytes_prefixed(&mut self, bytes: &[u8], kind: LengthKind) -> Result<()> {
+        self.trace_write("write_bytes_prefixed", bytes, |buf| {
+            buf.try_write_length(kind, bytes.len())?;
+            buf.try_write_bytes(bytes)
+        })
     }
 
-    /// Read a 32 bits floating point value. The program crash if not enough bytes are available
-    pub fn read_f32(&mut self) -> f32 {
-        self.flush_bit();
-        assert!(self.rpos + 4 <= self.data.len());
-        let range = self.rpos..self.rpos + 4;
-        self.rpos += 4;
-        BigEndian::read_f32(&self.data[range])
+    /// Writes a UTF-8 string prefixed with its length in bytes, using the given encoding for the length.
+    /// The program crash if the length does not fit in the prefix.
+    pub fn write_str_prefixed(&mut self, val: &str, kind: LengthKind) {
+        unwrap(self.try_write_str_prefixed(val, kind))
     }
 
-    /// Read a 64 bits floating point value. The program crash if not enough bytes are available
-    pub fn read_f64(&mut self) -> f64 {
-        self.flush_bit();
-        assert!(self.rpos + 8 <= self.data.len());
-        let range = self.rpos..self.rpos + 8;
-        self.rpos += 8;
-        BigEndian::read_f64(&self.data[range])
+    /// Same as `write_str_prefixed()`, but return an error if the length does not fit
+    /// in the prefix. Nothing is written on error.
+    pub fn try_write_str_prefixed(&mut self, val: &str, kind: LengthKind) -> Result<()> {
+        self.trace_write("write_str_prefixed", val, |buf| buf.try_write_bytes_prefixed(val.as_bytes(), kind))
     }
 
-    /// Read a string.
-    ///
-    /// *Note* : First it reads a 32 bits value representing the size, the read 'size' raw bytes.
-    pub fn read_string(&mut self) -> String {
-        let size = self.read_u32();
-        String::from_utf8(self.read_bytes(size as usize)).unwrap()
+    /// Writes a byte array prefixed with its length as a LEB128 variable-length integer.
+    pub fn write_var_bytes(&mut self, bytes: &[u8]) {
+        unwrap(self.try_write_var_bytes(bytes))
     }
 
-    /// Read a null-terminated string.
-    pub fn read_ntstr(&mut self) -> String {
-        let mut string = String::new();
+    /// Same as `write_var_bytes()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_var_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.trace_write("write_var_bytes", bytes, |buf| {
+            buf.try_write_var_u64(bytes.len() as u64)?;
+            buf.try_write_bytes(bytes)
+        })
+    }
 
-        let mut current = self.read_u8();
-        while current != 0 {
-            string.push(current as char);
-            current = self.read_u8();
-        }
+    /// Writes a UTF-8 string prefixed with its length in bytes as a LEB128 variable-length integer.
+    pub fn write_var_str(&mut self, val: &str) {
+        unwrap(self.try_write_var_str(val))
+    }
 
-        string
+    /// Same as `write_var_str()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_var_str(&mut self, val: &str) -> Result<()> {
+        self.trace_write("write_var_str", val, |buf| buf.try_write_var_bytes(val.as_bytes()))
     }
 
-    /// Read a null-bookended string.
-    pub fn read_dntstr(&mut self) -> String {
-        let mut string = String::new();
+    /// Overwrite bytes at the given position without moving the cursors.
+    /// The program crash if the bytes do not fit in the buffer
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x0, 0x0, 0x0, 0x0]);
+    /// buffer.put_bytes_at(1, &[0x1, 0x2]);
+    /// buffer.put_u8_at(3, 0x3);
+    /// assert_eq!(buffer.to_bytes(), vec![0x0, 0x1, 0x2, 0x3]);
+    /// assert_eq!(buffer.get_u16_at(2), 0x0203);
+    /// ```
+    pub fn put_bytes_at(&mut self, pos: usize, bytes: &[u8]) {
+        unwrap(self.try_put_bytes_at(pos, bytes))
+    }
+
+    /// Same as `put_bytes_at()`, but return an error if the bytes do not fit in the buffer
+    pub fn try_put_bytes_at(&mut self, pos: usize, bytes: &[u8]) -> Result<()> {
+        self.check_bounds(pos, bytes.len())?;
+        self.data.bytes_mut()[pos..pos + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
 
-        assert_eq!(0, self.read_u8());
+impl<S: Storage> DataBuffer<S> {
+    // Read operations
 
-        let mut current = self.read_u8();
-        while current != 0 {
-            string.push(current as char);
-            current = self.read_u8();
+    /// Flush the pending bits, then check that `size` bytes are available for reading
+    /// and return them, moving the reading cursor past them.
+    /// The cursor is left untouched if not enough bytes are available.
+    fn take(&mut self, size: usize) -> Result<&[u8]> {
+        let start = self.peek_start(size)?;
+        self.rpos = start + size;
+        self.rbit = 0;
+        Ok(&self.data[start..start + size])
+    }
+
+    /// Return the position of the next byte a byte read would return,
+    /// checking that at least `size` bytes are available from there.
+    fn peek_start(&self, size: usize) -> Result<usize> {
+        let start = self.remaining_start();
+        let remaining = self.data.len().saturating_sub(start);
+        if size > remaining {
+            return Err(Error::UnexpectedEof { needed: size, remaining });
         }
+        Ok(start)
+    }
 
-        string
+    /// Return a read-only view of the buffer, sharing its bytes and cursors but not its tracer
+    fn view(&self) -> DataBuffer<&[u8]> {
+        DataBuffer {
+            data: &self.data[..],
+            wpos: self.wpos,
+            rpos: self.rpos,
+            rbit: self.rbit,
+            wbit: self.wbit,
+            header: PacketHeader::NORMAL,
+            tracer: None,
+            undo: None
+        }
     }
 
-    /// Reads a smart from the buffer, which is a dynamically-sized unit with a max value of 32768.
-    pub fn read_smart(&mut self) -> u16 {
-        if self.data[self.rpos] >= 128 {
-            return self.read_u16().wrapping_sub(32768);
+    /// Take exactly N bytes and copy them into an array.
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+
+    /// Take up to N bytes and copy them into an array, the missing bytes left as zeros.
+    fn take_padded<const N: usize>(&mut self) -> [u8; N] {
+        let start = self.remaining_start();
+        let size = N.min(self.data.len().saturating_sub(start));
+        let mut bytes = [0; N];
+        bytes[..size].copy_from_slice(&self.data[start..start + size]);
+        self.rpos = start + size;
+        self.rbit = 0;
+        bytes
+    }
+
+    /// Read a defined amount of raw bytes. The program crash if not enough bytes are available
+    pub fn read_bytes(&mut self, size: usize) -> Vec<u8> {
+        unwrap(self.try_read_bytes(size))
+    }
+
+    /// Same as `read_bytes()`, but return an error if not enough bytes are available
+    pub fn try_read_bytes(&mut self, size: usize) -> Result<Vec<u8>> {
+        self.trace_read("read_bytes", |buf| Ok(buf.take(size)?.to_vec()))
+    }
+
+    /// Same as `read_bytes()`, but return a slice borrowed from the buffer instead of copying the bytes.
+    pub fn read_slice(&mut self, size: usize) -> &[u8] {
+        unwrap(self.try_read_slice(size))
+    }
+
+    /// Same as `try_read_bytes()`, but return a slice borrowed from the buffer instead of copying the bytes.
+    pub fn try_read_slice(&mut self, size: usize) -> Result<&[u8]> {
+        let start = self.remaining_start();
+        let result = match self.take(size).map(|_| ()) {
+            Ok(()) => Ok(&self.data[start..start + size]),
+            Err(err) => Err(err)
+        };
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.record_result("read_slice", start, self.rpos, &result);
         }
-        else {
-            return self.read_u8() as u16;
+        result
+    }
+
+    /// Read all the bytes from the reading cursor to the end of the buffer
+    pub fn read_remaining(&mut self) -> Vec<u8> {
+        unwrap(self.try_read_bytes(self.remaining()))
+    }
+
+    /// Move the reading cursor n bytes forward. The program crash if not enough bytes are available
+    pub fn skip(&mut self, n: usize) {
+        unwrap(self.try_skip(n))
+    }
+
+    /// Same as `skip()`, but return an error if not enough bytes are available
+    pub fn try_skip(&mut self, n: usize) -> Result<()> {
+        self.take(n).map(|_| ())
+    }
+
+    /// Read one byte. The program crash if not enough bytes are available
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x1]);
+    /// let value = buffer.read_u8(); //Value contains 1
+    /// ```
+    pub fn read_u8(&mut self) -> u8 {
+        unwrap(self.try_read_u8())
+    }
+
+    /// Same as `read_u8()`, but return an error if not enough bytes are available
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x1]);
+    /// assert_eq!(buffer.try_read_u8(), Ok(1));
+    /// assert!(buffer.try_read_u8().is_err());
+    /// ```
+    pub fn try_read_u8(&mut self) -> Result<u8> {
+        self.trace_read("read_u8", |buf| Ok(buf.take(1)?[0]))
+    }
+
+    /// Same as `read_u8()` but for signed values
+    pub fn read_i8(&mut self) -> i8 {
+        unwrap(self.try_read_i8())
+    }
+
+    /// Same as `try_read_u8()` but for signed values
+    pub fn try_read_i8(&mut self) -> Result<i8> {
+        self.trace_read("read_i8", |buf| Ok(buf.try_read_u8()? as i8))
+    }
+
+    /// Read a 2-bytes long value. The program crash if not enough bytes are available
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x0, 0x1]);
+    /// let value = buffer.read_u16(); //Value contains 1
+    /// ```
+    pub fn read_u16(&mut self) -> u16 {
+        unwrap(self.try_read_u16())
+    }
+
+    /// Same as `read_u16()`, but return an error if not enough bytes are available
+    pub fn try_read_u16(&mut self) -> Result<u16> {
+        self.trace_read("read_u16", |buf| Ok(BigEndian::read_u16(buf.take(2)?)))
+    }
+
+    /// Same as `read_u16()` but for signed values
+    pub fn read_i16(&mut self) -> i16 {
+        unwrap(self.try_read_i16())
+    }
+
+    /// Same as `try_read_u16()` but for signed values
+    pub fn try_read_i16(&mut self) -> Result<i16> {
+        self.trace_read("read_i16", |buf| Ok(buf.try_read_u16()? as i16))
+    }
+
+    /// Read a four-bytes long value. The program crash if not enough bytes are available
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x0, 0x0, 0x0, 0x1]);
+    /// let value = buffer.read_u32(); // Value contains 1
+    /// ```
+    pub fn read_u32(&mut self) -> u32 {
+        unwrap(self.try_read_u32())
+    }
+
+    /// Same as `read_u32()`, but return an error if not enough bytes are available
+    pub fn try_read_u32(&mut self) -> Result<u32> {
+        self.trace_read("read_u32", |buf| Ok(BigEndian::read_u32(buf.take(4)?)))
+    }
+
+    /// Same as `read_u32()` but for signed values
+    pub fn read_i32(&mut self) -> i32 {
+        unwrap(self.try_read_i32())
+    }
+
+    /// Same as `try_read_u32()` but for signed values
+    pub fn try_read_i32(&mut self) -> Result<i32> {
+        self.trace_read("read_i32", |buf| Ok(buf.try_read_u32()? as i32))
+    }
+
+    /// Read an eight bytes long value. The program crash if not enough bytes are available
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x1]);
+    /// let value = buffer.read_u64(); //Value contains 1
+    /// ```
+    pub fn read_u64(&mut self) -> u64 {
+        unwrap(self.try_read_u64())
+    }
+
+    /// Same as `read_u64()`, but return an error if not enough bytes are available
+    pub fn try_read_u64(&mut self) -> Result<u64> {
+        self.trace_read("read_u64", |buf| Ok(BigEndian::read_u64(buf.take(8)?)))
+    }
+
+    /// Same as `read_u64()` but for signed values
+    pub fn read_i64(&mut self) -> i64 {
+        unwrap(self.try_read_i64())
+    }
+
+    /// Same as `try_read_u64()` but for signed values
+    pub fn try_read_i64(&mut self) -> Result<i64> {
+        self.trace_read("read_i64", |buf| Ok(buf.try_read_u64()? as i64))
+    }
+
+    /// Read a 32 bits floating point value. The program crash if not enough bytes are available
+    pub fn read_f32(&mut self) -> f32 {
+        unwrap(self.try_read_f32())
+    }
+
+    /// Same as `read_f32()`, but return an error if not enough bytes are available
+    pub fn try_read_f32(&mut self) -> Result<f32> {
+        self.trace_read("read_f32", |buf| Ok(BigEndian::read_f32(buf.take(4)?)))
+    }
+
+    /// Read a 64 bits floating point value. The program crash if not enough bytes are available
+    pub fn read_f64(&mut self) -> f64 {
+        unwrap(self.try_read_f64())
+    }
+
+    /// Same as `read_f64()`, but return an error if not enough bytes are available
+    pub fn try_read_f64(&mut self) -> Result<f64> {
+        self.trace_read("read_f64", |buf| Ok(BigEndian::read_f64(buf.take(8)?)))
+    }
+
+    /// Read a string.
+    ///
+    /// *Note* : First it reads a 32 bits value representing the size, the read 'size' raw bytes.
+    pub fn read_string(&mut self) -> String {
+        unwrap(self.try_read_string())
+    }
+
+    /// Same as `read_string()`, but return an error if not enough bytes are available
+    /// or if the string is not valid UTF-8. The cursor is left untouched on error.
+    pub fn try_read_string(&mut self) -> Result<String> {
+        self.trace_read("read_string", |buf| buf.try_read_str().map(str::to_owned))
+    }
+
+    /// Same as `read_string()`, but replace invalid UTF-8 sequences with `U+FFFD`
+    /// instead of crashing.
+    pub fn read_string_lossy(&mut self) -> String {
+        unwrap(self.try_read_string_lossy())
+    }
+
+    /// Same as `read_string_lossy()`, but return an error if not enough bytes are available.
+    /// The cursor is left untouched on error.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x0, 0x0, 0x0, 0x2, 0x61, 0xFF]);
+    /// assert_eq!(buffer.try_read_string_lossy(), Ok("a\u{FFFD}".to_string()));
+    /// ```
+    pub fn try_read_string_lossy(&mut self) -> Result<String> {
+        self.trace_read("read_string_lossy", |buf| buf.try_read_string_prefixed_lossy(LengthKind::U32, usize::MAX))
+    }
+
+    /// Same as `read_string()`, but return a string borrowed from the buffer instead of copying it.
+    pub fn read_str(&mut self) -> &str {
+        unwrap(self.try_read_str())
+    }
+
+    /// Same as `try_read_string()`, but return a string borrowed from the buffer instead of copying it.
+    pub fn try_read_str(&mut self) -> Result<&str> {
+        self.try_read_str_as("read_str", LengthKind::U32, usize::MAX)
+    }
+
+    /// Read `size` bytes as a UTF-8 string borrowed from the buffer, and trace the read
+    /// under the given name. On error, the cursors are restored to the given mark.
+    fn try_read_utf8(&mut self, op: &'static str, mark: Mark, size: usize) -> Result<&str> {
+        let op_start = if mark.rbit > 0 { mark.rpos + 1 } else { mark.rpos };
+        let start = self.remaining_start();
+        if let Err(err) = self.take(size).map(|_| ()) {
+            self.reset(mark);
+            if let Some(tracer) = self.tracer.as_mut() {
+                tracer.record_error(op, op_start, &err);
+            }
+            return Err(err);
+        }
+
+        let result = match core::str::from_utf8(&self.data[start..start + size]) {
+            Ok(string) => Ok(string),
+            Err(err) => {
+                self.rpos = mark.rpos;
+                self.rbit = mark.rbit;
+                Err(Error::InvalidUtf8 { offset: start + err.valid_up_to() })
+            }
+        };
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.record_result(op, op_start, self.rpos, &result);
         }
+        result
+    }
+
+    /// Read a null-terminated string.
+    pub fn read_ntstr(&mut self) -> String {
+        unwrap(self.try_read_ntstr())
+    }
+
+    /// Same as `read_ntstr()`, but return an error if the terminator is missing.
+    /// The cursor is left untouched on error.
+    pub fn try_read_ntstr(&mut self) -> Result<String> {
+        self.trace_read("read_ntstr", |buf| buf.transaction(|buf| {
+            let mut string = String::new();
+
+            let mut current = buf.try_read_u8()?;
+            while current != 0 {
+                string.push(current as char);
+                current = buf.try_read_u8()?;
+            }
+
+            Ok(string)
+        }))
+    }
+
+    /// Read a null-bookended string.
+    pub fn read_dntstr(&mut self) -> String {
+        unwrap(self.try_read_dntstr())
+    }
+
+    /// Same as `read_dntstr()`, but return an error if the string is not properly bookended.
+    /// The cursor is left untouched on error.
+    pub fn try_read_dntstr(&mut self) -> Result<String> {
+        self.trace_read("read_dntstr", |buf| buf.transaction(|buf| {
+            let offset = buf.remaining_start();
+            let found = buf.try_read_u8()?;
+            if found != 0 {
+                return Err(Error::UnexpectedByte { offset, expected: 0, found });
+            }
+            buf.try_read_ntstr()
+        }))
+    }
+
+    /// Reads a smart from the buffer, which is a dynamically-sized unit with a max value of 32768.
+    pub fn read_smart(&mut self) -> u16 {
+        unwrap(self.try_read_smart())
+    }
+
+    /// Same as `read_smart()`, but return an error if not enough bytes are available
+    pub fn try_read_smart(&mut self) -> Result<u16> {
+        self.trace_read("read_smart", |buf| {
+            if buf.try_peek_u8()? >= 128 {
+                Ok(buf.try_read_u16()?.wrapping_sub(32768))
+            }
+            else {
+                Ok(buf.try_read_u8()? as u16)
+            }
+        })
     }
 
     /// Reads a medium from the buffer, which is a tribyte word.
     pub fn read_medium(&mut self) -> u32 {
-        return ((self.read_u8() as u32) << 16) + ((self.read_u8() as u32) << 8) + (self.read_u8() as u32);
+        unwrap(self.try_read_medium())
+    }
+
+    /// Same as `read_medium()`, but return an error if not enough bytes are available
+    pub fn try_read_medium(&mut self) -> Result<u32> {
+        self.trace_read("read_medium", |buf| {
+            let bytes = buf.take(3)?;
+            Ok(((bytes[0] as u32) << 16) + ((bytes[1] as u32) << 8) + (bytes[2] as u32))
+        })
     }
 
     /// Reads an inverted-signededness u8 from the buffer.
     pub fn read_u8_neg(&mut self) -> u8 {
-        return -self.read_i8() as u8;   
+        unwrap(self.try_read_u8_neg())
+    }
+
+    /// Same as `read_u8_neg()`, but return an error if not enough bytes are available
+    pub fn try_read_u8_neg(&mut self) -> Result<u8> {
+        self.trace_read("read_u8_neg", |buf| Ok(buf.try_read_u8()?.wrapping_neg()))
     }
 
     /// Reads an inverted-signededness i8 from the buffer.
     pub fn read_i8_neg(&mut self) -> i8 {
-        return self.read_i8().wrapping_neg();
+        unwrap(self.try_read_i8_neg())
+    }
+
+    /// Same as `read_i8_neg()`, but return an error if not enough bytes are available
+    pub fn try_read_i8_neg(&mut self) -> Result<i8> {
+        self.trace_read("read_i8_neg", |buf| Ok(buf.try_read_i8()?.wrapping_neg()))
     }
 
     /// Reads a u8 + 128 from the buffer, and subtracts the extra 128.
     pub fn read_u8_add(&mut self) -> u8 {
-        return self.read_u8().wrapping_sub(128);
+        unwrap(self.try_read_u8_add())
+    }
+
+    /// Same as `read_u8_add()`, but return an error if not enough bytes are available
+    pub fn try_read_u8_add(&mut self) -> Result<u8> {
+        self.trace_read("read_u8_add", |buf| Ok(buf.try_read_u8()?.wrapping_sub(128)))
     }
 
     /// Reads a i8 + 128 from the buffer, and subtracts the extra 128.
     pub fn read_i8_add(&mut self) -> i8 {
-        return self.read_u8().wrapping_sub(128) as i8;
+        unwrap(self.try_read_i8_add())
+    }
+
+    /// Same as `read_i8_add()`, but return an error if not enough bytes are available
+    pub fn try_read_i8_add(&mut self) -> Result<i8> {
+        self.trace_read("read_i8_add", |buf| Ok(buf.try_read_u8()?.wrapping_sub(128) as i8))
     }
 
     /// Reads a u8 - 128 from the buffer, and adds back the missing 128.
     pub fn read_u8_sub(&mut self) -> u8 {
-        return self.read_u8().wrapping_add(128) as u8;
+        unwrap(self.try_read_u8_sub())
+    }
+
+    /// Same as `read_u8_sub()`, but return an error if not enough bytes are available
+    pub fn try_read_u8_sub(&mut self) -> Result<u8> {
+        self.trace_read("read_u8_sub", |buf| Ok(buf.try_read_u8()?.wrapping_add(128)))
     }
 
     /// Reads a i8 - 128 from the buffer, and adds back the missing 128.
     pub fn read_i8_sub(&mut self) -> i8 {
-        return self.read_i8().wrapping_add(127).wrapping_add(1) as i8;
+        unwrap(self.try_read_i8_sub())
+    }
+
+    /// Same as `read_i8_sub()`, but return an error if not enough bytes are available
+    pub fn try_read_i8_sub(&mut self) -> Result<i8> {
+        self.trace_read("read_i8_sub", |buf| Ok(buf.try_read_i8()?.wrapping_add(127).wrapping_add(1)))
     }
 
     /// Reads a little-endian u16 from the buffer.
+    /// The missing bytes are read as zeros if the buffer is too short.
     pub fn read_u16_le(&mut self) -> u16 {
-        let mut bytes: [u8; 2] = [0; 2];
-        let _ = self.read(&mut bytes);
-        return u16::from_le_bytes(bytes);
+        unwrap(self.trace_read("read_u16_le", |buf| Ok(u16::from_le_bytes(buf.take_padded()))))
+    }
+
+    /// Same as `read_u16_le()`, but return an error if not enough bytes are available
+    pub fn try_read_u16_le(&mut self) -> Result<u16> {
+        self.trace_read("read_u16_le", |buf| Ok(u16::from_le_bytes(buf.take_array()?)))
     }
 
     /// Reads a little-endian i16 from the buffer.
+    /// The missing bytes are read as zeros if the buffer is too short.
     pub fn read_i16_le(&mut self) -> i16 {
-        let mut bytes: [u8; 2] = [0; 2];
-        let _ = self.read(&mut bytes);
-        return i16::from_le_bytes(bytes);
+        unwrap(self.trace_read("read_i16_le", |buf| Ok(i16::from_le_bytes(buf.take_padded()))))
+    }
+
+    /// Same as `read_i16_le()`, but return an error if not enough bytes are available
+    pub fn try_read_i16_le(&mut self) -> Result<i16> {
+        self.trace_read("read_i16_le", |buf| Ok(i16::from_le_bytes(buf.take_array()?)))
     }
 
     /// Reads a little-endian u16 + 128 from the buffer, and subtracts the extra 128.
+    /// The missing bytes are read as zeros if the buffer is too short.
     pub fn read_u16_le_add(&mut self) -> u16 {
-        let mut bytes: [u8; 2] = [0; 2];
-        let _ = self.read(&mut bytes);
-        bytes[0] = bytes[0].wrapping_sub(128);
-        return u16::from_le_bytes(bytes);
+        unwrap(self.trace_read("read_u16_le_add", |buf| {
+            let mut bytes: [u8; 2] = buf.take_padded();
+            bytes[0] = bytes[0].wrapping_sub(128);
+            Ok(u16::from_le_bytes(bytes))
+        }))
+    }
+
+    /// Same as `read_u16_le_add()`, but return an error if not enough bytes are available
+    pub fn try_read_u16_le_add(&mut self) -> Result<u16> {
+        self.trace_read("read_u16_le_add", |buf| {
+            let mut bytes: [u8; 2] = buf.take_array()?;
+            bytes[0] = bytes[0].wrapping_sub(128);
+            Ok(u16::from_le_bytes(bytes))
+        })
     }
 
     /// Reads a little-endian i16 + 128 from the buffer, and subtracts the extra 128.
+    /// The missing bytes are read as zeros if the buffer is too short.
     pub fn read_i16_le_add(&mut self) -> i16 {
-        let mut bytes: [u8; 2] = [0; 2];
-        let _ = self.read(&mut bytes);
-        bytes[0] = bytes[0].wrapping_sub(128);
-        return i16::from_le_bytes(bytes);
+        unwrap(self.trace_read("read_i16_le_add", |buf| {
+            let mut bytes: [u8; 2] = buf.take_padded();
+            bytes[0] = bytes[0].wrapping_sub(128);
+            Ok(i16::from_le_bytes(bytes))
+        }))
+    }
+
+    /// Same as `read_i16_le_add()`, but return an error if not enough bytes are available
+    pub fn try_read_i16_le_add(&mut self) -> Result<i16> {
+        self.trace_read("read_i16_le_add", |buf| {
+            let mut bytes: [u8; 2] = buf.take_array()?;
+            bytes[0] = bytes[0].wrapping_sub(128);
+            Ok(i16::from_le_bytes(bytes))
+        })
     }
 
     /// Reads a little-endian u32 from the buffer.
+    /// The missing bytes are read as zeros if the buffer is too short.
     pub fn read_u32_le(&mut self) -> u32 {
-        let mut bytes: [u8; 4] = [0; 4];
-        let _ = self.read(&mut bytes);
-        return u32::from_le_bytes(bytes);
+        unwrap(self.trace_read("read_u32_le", |buf| Ok(u32::from_le_bytes(buf.take_padded()))))
+    }
+
+    /// Same as `read_u32_le()`, but return an error if not enough bytes are available
+    pub fn try_read_u32_le(&mut self) -> Result<u32> {
+        self.trace_read("read_u32_le", |buf| Ok(u32::from_le_bytes(buf.take_array()?)))
     }
 
     /// Reads a little-endian u32 from the buffer.
+    /// The missing bytes are read as zeros if the buffer is too short.
     pub fn read_i32_le(&mut self) -> i32 {
-        let mut bytes: [u8; 4] = [0; 4];
-        let _ = self.read(&mut bytes);
-        return i32::from_le_bytes(bytes);
+        unwrap(self.trace_read("read_i32_le", |buf| Ok(i32::from_le_bytes(buf.take_padded()))))
+    }
+
+    /// Same as `read_i32_le()`, but return an error if not enough bytes are available
+    pub fn try_read_i32_le(&mut self) -> Result<i32> {
+        self.trace_read("read_i32_le", |buf| Ok(i32::from_le_bytes(buf.take_array()?)))
     }
 
     /// Reads a mixed-endian u32 from the buffer.
     pub fn read_u32_me(&mut self) -> u32 {
-        return ((self.read_u8() as u32) << 16) + ((self.read_u8() as u32) << 24) + ((self.read_u8() as u32)) + ((self.read_u8() as u32) << 8);
+        unwrap(self.try_read_u32_me())
+    }
+
+    /// Same as `read_u32_me()`, but return an error if not enough bytes are available
+    pub fn try_read_u32_me(&mut self) -> Result<u32> {
+        self.trace_read("read_u32_me", |buf| {
+            let bytes = buf.take(4)?;
+            Ok(((bytes[0] as u32) << 16) + ((bytes[1] as u32) << 24) + (bytes[2] as u32) + ((bytes[3] as u32) << 8))
+        })
     }
 
     /// Reads a reverse mixed-endian u32 from the buffer.
     pub fn read_u32_me_reversed(&mut self) -> u32 {
-        return ((self.read_u8() as u32) << 8) + (self.read_u8() as u32) + ((self.read_u8() as u32) << 24) + ((self.read_u8() as u32) << 16);
+        unwrap(self.try_read_u32_me_reversed())
+    }
+
+    /// Same as `read_u32_me_reversed()`, but return an error if not enough bytes are available
+    pub fn try_read_u32_me_reversed(&mut self) -> Result<u32> {
+        self.trace_read("read_u32_me_reversed", |buf| {
+            let bytes = buf.take(4)?;
+            Ok(((bytes[0] as u32) << 8) + (bytes[1] as u32) + ((bytes[2] as u32) << 24) + ((bytes[3] as u32) << 16))
+        })
+    }
+
+    /// Decode an unsigned LEB128 variable-length integer of at most `bits` bits.
+    /// Overlong encodings and values which do not fit in `bits` bits are rejected.
+    fn try_read_var(&mut self, bits: u32) -> Result<u64> {
+        self.transaction(|buf| {
+            let offset = buf.remaining_start();
+            let mut value = 0u64;
+            let mut shift = 0;
+            loop {
+                let byte = buf.try_read_u8()?;
+                let payload = (byte & 0x7F) as u64;
+                if shift + 7 > bits && payload >> (bits - shift) != 0 {
+                    return Err(Error::VarIntOverflow { offset });
+                }
+                value |= payload << shift;
+                shift += 7;
+
+                if byte & 0x80 == 0 {
+                    if byte == 0 && shift > 7 {
+                        return Err(Error::OverlongVarInt { offset });
+                    }
+                    return Ok(value);
+                }
+                if shift >= bits {
+                    return Err(Error::VarIntOverflow { offset });
+                }
+            }
+        })
+    }
+
+    /// Reads an unsigned LEB128 variable-length integer from the buffer.
+    /// The program crash if the value is malformed or does not fit in 32 bits.
+    pub fn read_var_u32(&mut self) -> u32 {
+        unwrap(self.try_read_var_u32())
+    }
+
+    /// Same as `read_var_u32()`, but return an error if the value is malformed or does not fit.
+    /// The cursor is left untouched on error.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0xAC, 0x02, 0x80, 0x00]);
+    /// assert_eq!(buffer.try_read_var_u32(), Ok(300));
+    /// assert!(buffer.try_read_var_u32().is_err()); // 0 encoded in two bytes
+    /// ```
+    pub fn try_read_var_u32(&mut self) -> Result<u32> {
+        self.trace_read("read_var_u32", |buf| Ok(buf.try_read_var(32)? as u32))
+    }
+
+    /// Reads an unsigned LEB128 variable-length integer from the buffer.
+    /// The program crash if the value is malformed or does not fit in 64 bits.
+    pub fn read_var_u64(&mut self) -> u64 {
+        unwrap(self.try_read_var_u64())
+    }
+
+    /// Same as `read_var_u64()`, but return an error if the value is malformed or does not fit.
+    pub fn try_read_var_u64(&mut self) -> Result<u64> {
+        self.trace_read("read_var_u64", |buf| buf.try_read_var(64))
+    }
+
+    /// Reads a zigzag-encoded LEB128 variable-length integer from the buffer.
+    pub fn read_var_i32(&mut self) -> i32 {
+        unwrap(self.try_read_var_i32())
+    }
+
+    /// Same as `read_var_i32()`, but return an error if the value is malformed or does not fit.
+    pub fn try_read_var_i32(&mut self) -> Result<i32> {
+        self.trace_read("read_var_i32", |buf| {
+            let val = buf.try_read_var_u32()?;
+            Ok((val >> 1) as i32 ^ -((val & 1) as i32))
+        })
+    }
+
+    /// Reads a zigzag-encoded LEB128 variable-length integer from the buffer.
+    pub fn read_var_i64(&mut self) -> i64 {
+        unwrap(self.try_read_var_i64())
+    }
+
+    /// Same as `read_var_i64()`, but return an error if the value is malformed or does not fit.
+    pub fn try_read_var_i64(&mut self) -> Result<i64> {
+        self.trace_read("read_var_i64", |buf| {
+            let val = buf.try_read_var_u64()?;
+            Ok((val >> 1) as i64 ^ -((val & 1) as i64))
+        })
+    }
+
+    /// Reads a length prefix with the given encoding.
+    /// The program crash if not enough bytes are available
+    pub fn read_length(&mut self, kind: LengthKind) -> usize {
+        unwrap(self.try_read_length(kind))
+    }
+
+    /// Same as `read_length()`, but return an error if not enough bytes are available
+    /// or the length is malformed
+    pub fn try_read_length(&mut self, kind: LengthKind) -> Result<usize> {
+        self.trace_read("read_length", |buf| {
+            let length = match kind {
+                LengthKind::U8 => buf.try_read_u8()? as u32,
+                LengthKind::U8Neg => buf.try_read_u8_neg()? as u32,
+                LengthKind::U8Add => buf.try_read_u8_add()? as u32,
+                LengthKind::U16 => buf.try_read_u16()? as u32,
+                LengthKind::U32 => buf.try_read_u32()?,
+                LengthKind::Smart => buf.try_read_smart()? as u32,
+                LengthKind::VarInt => buf.try_read_var_u32()?
+            };
+            Ok(length as usize)
+        })
+    }
+
+    /// Reads a byte array prefixed with its length, using the given encoding for the length.
+    /// The program crash if the length is larger than `max_len` or not enough bytes are available.
+    pub fn read_bytes_prefixed(&mut self, kind: LengthKind, max_len: usize) -> Vec<u8> {
+        unwrap(self.try_read_bytes_prefixed(kind, max_len))
+    }
+
+    /// Same as `read_bytes_prefixed()`, but return an error instead of crashing.
+    /// The length is checked before anything is allocated, and the cursor is left untouched on error.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0xFF, 0xFF, 0xFF, 0xFF]);
+    /// assert!(buffer.try_read_bytes_prefixed(LengthKind::U32, 1024).is_err());
+    /// ```
+    pub fn try_read_bytes_prefixed(&mut self, kind: LengthKind, max_len: usize) -> Result<Vec<u8>> {
+        self.trace_read("read_bytes_prefixed", |buf| buf.transaction(|buf| {
+            let size = buf.try_read_length_limited(kind, max_len)?;
+            buf.try_read_bytes(size)
+        }))
+    }
+
+    /// Reads a UTF-8 string prefixed with its length in bytes, using the given encoding for the length.
+    /// The program crash if the length is larger than `max_len`, not enough bytes are available or
+    /// the string is not valid UTF-8.
+    pub fn read_string_prefixed(&mut self, kind: LengthKind, max_len: usize) -> String {
+        unwrap(self.try_read_string_prefixed(kind, max_len))
+    }
+
+    /// Same as `read_string_prefixed()`, but return an error instead of crashing.
+    /// The length is checked before anything is allocated, and the cursor is left untouched on error.
+    pub fn try_read_string_prefixed(&mut self, kind: LengthKind, max_len: usize) -> Result<String> {
+        self.trace_read("read_string_prefixed", |buf| buf.try_read_str_prefixed(kind, max_len).map(str::to_owned))
+    }
+
+    /// Same as `read_string_prefixed()`, but replace invalid UTF-8 sequences with `U+FFFD`.
+    /// The program crash if the length is larger than `max_len` or not enough bytes are available.
+    pub fn read_string_prefixed_lossy(&mut self, kind: LengthKind, max_len: usize) -> String {
+        unwrap(self.try_read_string_prefixed_lossy(kind, max_len))
+    }
+
+    /// Same as `read_string_prefixed_lossy()`, but return an error instead of crashing.
+    /// The cursor is left untouched on error.
+    pub fn try_read_string_prefixed_lossy(&mut self, kind: LengthKind, max_len: usize) -> Result<String> {
+        self.trace_read("read_string_prefixed_lossy", |buf| buf.transaction(|buf| {
+            let size = buf.try_read_length_limited(kind, max_len)?;
+            Ok(String::from_utf8_lossy(buf.take(size)?).into_owned())
+        }))
+    }
+
+    /// Same as `read_string_prefixed()`, but return a string borrowed from the buffer instead of copying it.
+    pub fn read_str_prefixed(&mut self, kind: LengthKind, max_len: usize) -> &str {
+        unwrap(self.try_read_str_prefixed(kind, max_len))
+    }
+
+    /// Same as `try_read_string_prefixed()`, but return a string borrowed from the buffer instead of copying it.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x2, 0x68, 0x69]);
+    /// assert_eq!(buffer.try_read_str_prefixed(LengthKind::U8, 16), Ok("hi"));
+    /// ```
+    pub fn try_read_str_prefixed(&mut self, kind: LengthKind, max_len: usize) -> Result<&str> {
+        self.try_read_str_as("read_str_prefixed", kind, max_len)
+    }
+
+    /// Same as `try_read_str_prefixed()`, but trace the read under the given name
+    fn try_read_str_as(&mut self, op: &'static str, kind: LengthKind, max_len: usize) -> Result<&str> {
+        let mark = self.mark();
+        let start = self.remaining_start();
+        let size = match self.untraced(|buf| buf.try_read_length_limited(kind, max_len)) {
+            Ok(size) => size,
+            Err(err) => {
+                self.reset(mark);
+                if let Some(tracer) = self.tracer.as_mut() {
+                    tracer.record_error(op, start, &err);
+                }
+                return Err(err);
+            }
+        };
+        self.try_read_utf8(op, mark, size)
+    }
+
+    /// Read a length prefix and check it against the given limit
+    fn try_read_length_limited(&mut self, kind: LengthKind, max_len: usize) -> Result<usize> {
+        let length = self.try_read_length(kind)?;
+        if length > max_len {
+            return Err(Error::LengthLimitExceeded { length, max: max_len });
+        }
+        Ok(length)
+    }
+
+    /// Reads a byte array prefixed with its length as a LEB128 variable-length integer.
+    pub fn read_var_bytes(&mut self) -> Vec<u8> {
+        unwrap(self.try_read_var_bytes())
+    }
+
+    /// Same as `read_var_bytes()`, but return an error if the length is malformed or
+    /// not enough bytes are available. The cursor is left untouched on error.
+    pub fn try_read_var_bytes(&mut self) -> Result<Vec<u8>> {
+        self.trace_read("read_var_bytes", |buf| buf.transaction(|buf| {
+            let size = buf.try_read_var_u64()?;
+            buf.try_read_bytes(size.try_into().unwrap_or(usize::MAX))
+        }))
+    }
+
+    /// Reads a UTF-8 string prefixed with its length in bytes as a LEB128 variable-length integer.
+    pub fn read_var_str(&mut self) -> String {
+        unwrap(self.try_read_var_str())
+    }
+
+    /// Same as `read_var_str()`, but return an error if the length is malformed, not enough
+    /// bytes are available or the string is not valid UTF-8. The cursor is left untouched on error.
+    pub fn try_read_var_str(&mut self) -> Result<String> {
+        self.trace_read("read_var_str", |buf| {
+            let mark = buf.mark();
+            let size = buf.try_read_var_u64()?;
+            buf.try_read_utf8("read_var_str", mark, size.try_into().unwrap_or(usize::MAX)).map(str::to_owned)
+        })
     }
 
     // Other
 
-    /// Dump the byte buffer to a string.
-    pub fn to_string(&self) -> String {
-        let mut str = String::new();
-        for b in &self.data {
-            str = str + &format!("0x{:01$x} ", b, 2);
+    /// Return a copy of the bytes at the given position without moving the cursors.
+    /// The program crash if not enough bytes are available
+    pub fn get_bytes_at(&self, pos: usize, size: usize) -> Vec<u8> {
+        unwrap(self.try_get_bytes_at(pos, size))
+    }
+
+    /// Same as `get_bytes_at()`, but return an error if not enough bytes are available
+    pub fn try_get_bytes_at(&self, pos: usize, size: usize) -> Result<Vec<u8>> {
+        self.check_bounds(pos, size)?;
+        Ok(self.data[pos..pos + size].to_vec())
+    }
+
+    /// Check that `size` bytes starting at `pos` are inside the buffer
+    fn check_bounds(&self, pos: usize, size: usize) -> Result<()> {
+        match pos.checked_add(size) {
+            Some(end) if end <= self.data.len() => Ok(()),
+            _ => Err(Error::OutOfBounds { offset: pos, size, len: self.data.len() })
         }
-        str.pop();
-        str
     }
 
     /// Return the position of the reading cursor
@@ -676,7 +1888,7 @@ impl DataBuffer {
     /// Set the reading cursor position.
     /// *Note* : Set the reading cursor to `min(newPosition, self.len())` to prevent overflow
     pub fn set_rpos(&mut self, rpos: usize) {
-        self.rpos = std::cmp::min(rpos, self.data.len());
+        self.rpos = core::cmp::min(rpos, self.data.len());
     }
 
     /// Return the writing cursor position
@@ -687,7 +1899,63 @@ impl DataBuffer {
     /// Set the writing cursor position.
     /// *Note* : Set the writing cursor to `min(newPosition, self.len())` to prevent overflow
     pub fn set_wpos(&mut self, wpos: usize) {
-        self.wpos = std::cmp::min(wpos, self.data.len());
+        self.wpos = core::cmp::min(wpos, self.data.len());
+    }
+
+    /// Capture the current state of the reading and writing cursors, including
+    /// the bit positions, so it can be restored later with [`reset`](#method.reset).
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0xA0, 0x01]);
+    /// let mark = buffer.mark();
+    /// buffer.read_bits(3);
+    /// buffer.reset(mark);
+    /// assert_eq!(buffer.read_u8(), 0xA0);
+    /// ```
+    pub fn mark(&self) -> Mark {
+        Mark { rpos: self.rpos, rbit: self.rbit, wpos: self.wpos, wbit: self.wbit }
+    }
+
+    /// Restore the reading and writing cursors to a state captured by [`mark`](#method.mark).
+    ///
+    /// *Note* : Only the cursors are restored, bytes written since the mark are kept.
+    pub fn reset(&mut self, mark: Mark) {
+        self.rpos = core::cmp::min(mark.rpos, self.data.len());
+        self.rbit = mark.rbit;
+        self.wpos = core::cmp::min(mark.wpos, self.data.len());
+        self.wbit = mark.wbit;
+    }
+
+    /// Run the given closure on this buffer, restoring the cursors to their previous
+    /// state if it returns an error. This is useful to parse frames which may be incomplete.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x03, 0x01, 0x02]);
+    /// let frame = buffer.transaction(|buf| {
+    ///     let size = buf.read_u8() as usize;
+    ///     if !buf.has_remaining(size) {
+    ///         return Err("incomplete frame");
+    ///     }
+    ///     Ok(buf.read_bytes(size))
+    /// });
+    /// assert!(frame.is_err());
+    /// assert_eq!(buffer.get_rpos(), 0);
+    /// ```
+    pub fn transaction<T, E, F>(&mut self, f: F) -> core::result::Result<T, E>
+        where F: FnOnce(&mut Self) -> core::result::Result<T, E>
+    {
+        let mark = self.mark();
+        let result = f(self);
+        if result.is_err() {
+            self.reset(mark);
+        }
+        result
     }
 
     /// Returns a copy of this buffer's underlying [`Vec<u8>`].
@@ -697,7 +1965,8 @@ impl DataBuffer {
         self.data.to_vec()
     }
 
-    pub fn deconstruct(self) -> Vec<u8> {
+    /// Consumes the buffer and returns its storage, the underlying [`Vec<u8>`] by default.
+    pub fn deconstruct(self) -> S {
         self.data
     }
 
@@ -716,14 +1985,33 @@ impl DataBuffer {
     /// let value2 = buffer.read_bit(); //value2 contains false (eg: bit is 0)
     /// ```
     pub fn read_bit(&mut self) -> bool {
-        assert!(self.rpos <= self.data.len());
-        let bit = self.data[self.rpos] & (1 << 7 - self.rbit) != 0;
-        self.rbit += 1;
-        if self.rbit > 7 {
-            self.rbit = 0;
-            self.rpos += 1;
+        unwrap(self.try_read_bit())
+    }
+
+    /// Same as `read_bit()`, but return an error if no bit is available
+    pub fn try_read_bit(&mut self) -> Result<bool> {
+        self.trace_read_bits("read_bit", |buf| {
+            buf.check_bits(1)?;
+            let bit = buf.data[buf.rpos] & (1 << (7 - buf.rbit)) != 0;
+            buf.rbit += 1;
+            if buf.rbit > 7 {
+                buf.rbit = 0;
+                buf.rpos += 1;
+            }
+            Ok(bit)
+        })
+    }
+
+    /// Check that n bits are available for reading
+    fn check_bits(&self, n: usize) -> Result<()> {
+        let available = (self.data.len() * 8).saturating_sub(self.rpos * 8 + self.rbit);
+        if n > available {
+            return Err(Error::UnexpectedEof {
+                needed: (self.rbit + n).div_ceil(8),
+                remaining: self.data.len().saturating_sub(self.rpos)
+            });
         }
-        bit
+        Ok(())
     }
 
     /// Read n bits. an return the corresponding value an u64.
@@ -740,12 +2028,57 @@ impl DataBuffer {
     /// let value = buffer.read_bits(3); // value contains 4 (eg: 100b)
     /// ```
     pub fn read_bits(&mut self, n: u8) -> u64 {
-        // TODO : Assert that n <= 64
-        if n > 0 {
-            ((if self.read_bit() { 1 } else { 0 }) << n - 1) | self.read_bits(n - 1)
-        } else {
-            0
-        }
+        assert!(n <= 64, "cannot read more than 64 bits at once");
+        unwrap(self.try_read_bits(n))
+    }
+
+    /// Same as `read_bits()`, but return an error if more than 64 bits are requested or
+    /// not enough bits are available. The cursor is left untouched on error.
+    pub fn try_read_bits(&mut self, n: u8) -> Result<u64> {
+        self.trace_read_bits("read_bits", |buf| {
+            check_bit_count(n)?;
+            buf.check_bits(n as usize)?;
+            let mut value = 0;
+            for _ in 0..n {
+                value = (value << 1) | buf.try_read_bit()? as u64;
+            }
+            Ok(value)
+        })
+    }
+
+    /// Read n bits as a two's complement value and sign-extend it to an i64.
+    ///
+    /// **Note** : We cannot read more than 64 bits
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0xF0]); // 11110000b
+    /// let value = buffer.read_bits_signed(5); // value contains -2 (eg: 11110b)
+    /// ```
+    pub fn read_bits_signed(&mut self, n: u8) -> i64 {
+        unwrap(self.try_read_bits_signed(n))
+    }
+
+    /// Same as `read_bits_signed()`, but return an error if not enough bits are available
+    pub fn try_read_bits_signed(&mut self, n: u8) -> Result<i64> {
+        self.trace_read_bits("read_bits_signed", |buf| Ok(sign_extend(buf.try_read_bits(n)?, n)))
+    }
+
+    /// Advance the reading cursor by n bits without reading them.
+    /// The program crash if not enough bits are available.
+    pub fn skip_bits(&mut self, n: usize) {
+        unwrap(self.try_skip_bits(n))
+    }
+
+    /// Same as `skip_bits()`, but return an error if not enough bits are available
+    pub fn try_skip_bits(&mut self, n: usize) -> Result<()> {
+        self.check_bits(n)?;
+        let target = self.rpos * 8 + self.rbit + n;
+        self.rpos = target / 8;
+        self.rbit = target % 8;
+        Ok(())
     }
 
     /// Discard all the pending bits available for reading or writing and place the the corresponding cursor to the next byte.
@@ -773,7 +2106,9 @@ impl DataBuffer {
             self.wbit = 0
         }
     }
+}
 
+impl<S: StorageMut> DataBuffer<S> {
     /// Append 1 bit value to the buffer.
     /// The bit is happened like this :
     ///
@@ -781,21 +2116,33 @@ impl DataBuffer {
     /// ...| XXXXXXXX | 10000000 |....
     /// ```
     pub fn write_bit(&mut self, bit: bool) {
-        let size = self.wpos + 1;
-        if size > self.data.len() {
-            self.resize(size);
-        }
+        unwrap(self.try_write_bit(bit))
+    }
 
-        if bit {
-            self.data[self.wpos] |= 1 << (7 - self.wbit);
-        }
+    /// Same as `write_bit()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_bit(&mut self, bit: bool) -> Result<()> {
+        self.trace_write_bits("write_bit", &bit, |buf| {
+            let size = buf.wpos + 1;
+            if size > buf.data.len() {
+                buf.try_resize(size)?;
+            }
 
-        self.wbit += 1;
+            buf.save_overwritten(size);
+            if bit {
+                buf.data.bytes_mut()[buf.wpos] |= 1 << (7 - buf.wbit);
+            } else {
+                buf.data.bytes_mut()[buf.wpos] &= !(1 << (7 - buf.wbit));
+            }
 
-        if self.wbit > 7 {
-            self.wbit = 0;
-            self.wpos += 1;
-        }
+            buf.wbit += 1;
+
+            if buf.wbit > 7 {
+                buf.wbit = 0;
+                buf.wpos += 1;
+            }
+            Ok(())
+        })
     }
 
     /// Write the given value as a sequence of n bits
@@ -808,21 +2155,465 @@ impl DataBuffer {
     /// buffer.write_bits(4, 3); // append 100b
     /// ```
     pub fn write_bits(&mut self, value: u64, n: u8) {
-        if n > 0 {
-            self.write_bit((value >> n - 1) & 1 != 0);
-            self.write_bits(value, n - 1);
+        assert!(n <= 64, "cannot write more than 64 bits at once");
+        unwrap(self.try_write_bits(value, n))
+    }
+
+    /// Same as `write_bits()`, but return an error if more than 64 bits are requested or
+    /// the buffer cannot hold the value. Nothing is written on error.
+    pub fn try_write_bits(&mut self, value: u64, n: u8) -> Result<()> {
+        self.trace_write_bits("write_bits", &value, |buf| {
+            check_bit_count(n)?;
+            let size = (buf.wpos * 8 + buf.wbit + n as usize).div_ceil(8);
+            if size > buf.data.len() {
+                buf.try_resize(size)?;
+            }
+            for i in (0..n).rev() {
+                buf.try_write_bit((value >> i) & 1 != 0)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Write the given signed value as a sequence of n bits in two's complement.
+    /// Only the n lowest bits of the value are written.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::new();
+    /// buffer.write_bits_signed(-2, 5); // append 11110b
+    /// ```
+    pub fn write_bits_signed(&mut self, value: i64, n: u8) {
+        unwrap(self.try_write_bits_signed(value, n))
+    }
+
+    /// Same as `write_bits_signed()`, but return an error if the buffer cannot hold the value.
+    /// Nothing is written on error.
+    pub fn try_write_bits_signed(&mut self, value: i64, n: u8) -> Result<()> {
+        self.trace_write_bits("write_bits_signed", &value, |buf| buf.try_write_bits(value as u64, n))
+    }
+
+    /// Reserve n bits at the current writing bit position and return a handle to them.
+    /// The reserved bits are written as zeros and can be filled in later with
+    /// [`fill_bits`](#method.fill_bits), once the value is known.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::new();
+    /// let count = buffer.reserve_bits(8);
+    /// buffer.write_bits(3, 2);
+    /// buffer.write_bits(1, 2);
+    /// buffer.fill_bits(count, 2); // buffer contains [0x02, 0xD0]
+    /// ```
+    pub fn reserve_bits(&mut self, n: u8) -> BitReservation {
+        unwrap(self.try_reserve_bits(n))
+    }
+
+    /// Same as `reserve_bits()`, but return an error if the buffer cannot hold the bits.
+    pub fn try_reserve_bits(&mut self, n: u8) -> Result<BitReservation> {
+        let reservation = BitReservation { offset: self.wpos * 8 + self.wbit, bits: n };
+        self.try_write_bits(0, n)?;
+        Ok(reservation)
+    }
+
+    /// Fill previously reserved bits with the given value, without moving the cursors.
+    ///
+    /// **Note** : The program crash if the value does not fit in the reserved bits,
+    /// use [`try_fill_bits`](#method.try_fill_bits) to handle this case.
+    pub fn fill_bits(&mut self, reservation: BitReservation, value: u64) {
+        unwrap(self.try_fill_bits(reservation, value))
+    }
+
+    /// Same as `fill_bits()`, but return an error instead of crashing if the value
+    /// does not fit in the reserved bits.
+    pub fn try_fill_bits(&mut self, reservation: BitReservation, value: u64) -> Result<()> {
+        if value > reservation.max_value() {
+            return Err(Error::ValueOutOfRange { value, max: reservation.max_value() });
+        }
+        self.set_bits_at(reservation.offset, value, reservation.bits);
+        Ok(())
+    }
+}
+
+impl<S: Storage> DataBuffer<S> {
+    // Random access bit functions, these never move the reading or writing cursors.
+    // Bit indexes are counted from the start of the buffer, left to right.
+
+    /// Return the bit at the given bit index.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let buffer = DataBuffer::from_bytes(&vec![0x00, 0x40]); // 00000000 01000000b
+    /// assert!(buffer.get_bit(9));
+    /// ```
+    pub fn get_bit(&self, index: usize) -> bool {
+        assert!(index < self.data.len() * 8);
+        self.data[index / 8] & (1 << (7 - index % 8)) != 0
+    }
+
+    /// Read n bits starting at the given bit offset.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let buffer = DataBuffer::from_bytes(&vec![0x0F, 0x00]); // 00001111 00000000b
+    /// assert_eq!(buffer.get_bits_at(2, 4), 3); // 0011b
+    /// ```
+    pub fn get_bits_at(&self, bit_offset: usize, n: u8) -> u64 {
+        assert!(n <= 64, "cannot read more than 64 bits at once");
+        unwrap(self.try_get_bits_at(bit_offset, n))
+    }
+
+    /// Same as `get_bits_at()`, but return an error if more than 64 bits are requested
+    /// or the bits fall outside the buffer
+    pub fn try_get_bits_at(&self, bit_offset: usize, n: u8) -> Result<u64> {
+        check_bit_count(n)?;
+        self.check_bit_bounds(bit_offset, n)?;
+        let mut value = 0;
+        for i in 0..n as usize {
+            value = (value << 1) | self.get_bit(bit_offset + i) as u64;
+        }
+        Ok(value)
+    }
+
+    /// Check that the n bits starting at the given bit offset are inside the buffer
+    fn check_bit_bounds(&self, bit_offset: usize, n: u8) -> Result<()> {
+        self.check_bounds(bit_offset / 8, (bit_offset % 8 + n as usize).div_ceil(8))
+    }
+}
+
+impl<S: StorageMut> DataBuffer<S> {
+    /// Set the bit at the given bit index. The bit must be inside the buffer.
+    pub fn set_bit(&mut self, index: usize, bit: bool) {
+        assert!(index < self.data.len() * 8);
+        let mask = 1 << (7 - index % 8);
+        if bit {
+            self.data.bytes_mut()[index / 8] |= mask;
         } else {
-            self.write_bit((value & 1) != 0);
+            self.data.bytes_mut()[index / 8] &= !mask;
+        }
+    }
+
+    /// Overwrite n bits starting at the given bit offset with the given value.
+    /// All the bits must be inside the buffer.
+    pub fn set_bits_at(&mut self, bit_offset: usize, value: u64, n: u8) {
+        assert!(n <= 64, "cannot write more than 64 bits at once");
+        unwrap(self.try_set_bits_at(bit_offset, value, n))
+    }
+
+    /// Same as `set_bits_at()`, but return an error if more than 64 bits are requested
+    /// or the bits fall outside the buffer. Nothing is written on error.
+    pub fn try_set_bits_at(&mut self, bit_offset: usize, value: u64, n: u8) -> Result<()> {
+        check_bit_count(n)?;
+        self.check_bit_bounds(bit_offset, n)?;
+        for i in 0..n {
+            self.set_bit(bit_offset + (n - 1 - i) as usize, (value >> i) & 1 != 0);
+        }
+        Ok(())
+    }
+}
+
+/// Check that no more than 64 bits are read or written at once
+fn check_bit_count(n: u8) -> Result<()> {
+    if n > 64 {
+        return Err(Error::ValueOutOfRange { value: n as u64, max: 64 });
+    }
+    Ok(())
+}
+
+/// Encode the given value as an unsigned LEB128 variable-length integer into `out`,
+/// and return the number of bytes used.
+fn encode_var_u64(mut value: u64, out: &mut [u8; 10]) -> usize {
+    let mut size = 0;
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out[size] = byte;
+            return size + 1;
+        }
+        out[size] = byte | 0x80;
+        size += 1;
+    }
+}
+
+/// Unwrap the result of a fallible operation, crashing with the error message on failure.
+#[track_caller]
+fn unwrap<T>(result: Result<T>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) => panic!("{}", err)
+    }
+}
+
+/// Sign-extend the n lowest bits of the given value.
+fn sign_extend(value: u64, n: u8) -> i64 {
+    if n == 0 || n >= 64 {
+        return value as i64;
+    }
+    let shift = 64 - n;
+    ((value << shift) as i64) >> shift
+}
+
+/// Generate the `put_*_at` and `get_*_at` functions, along with their `try_` forms.
+/// The getters decode the bytes with the given function instead of going through the
+/// readers, so they do not need to borrow the buffer mutably.
+macro_rules! absolute_fns {
+    ($($put:ident, $try_put:ident, $get:ident, $try_get:ident => $write:ident($ty:ty), $size:expr, $decode:expr;)*) => {
+        impl<S: StorageMut> DataBuffer<S> {
+            $(
+                #[doc = concat!("Same as `", stringify!($write), "()`, but write at the given position without moving the cursors. The program crash if the value does not fit in the buffer")]
+                pub fn $put(&mut self, pos: usize, val: $ty) {
+                    unwrap(self.$try_put(pos, val))
+                }
+
+                #[doc = concat!("Same as `", stringify!($put), "()`, but return an error if the value does not fit in the buffer")]
+                pub fn $try_put(&mut self, pos: usize, val: $ty) -> Result<()> {
+                    self.check_bounds(pos, $size)?;
+                    let mark = self.mark();
+                    self.wpos = pos;
+                    self.wbit = 0;
+                    self.untraced(|buf| buf.$write(val));
+                    self.reset(mark);
+                    Ok(())
+                }
+            )*
+        }
+
+        impl<S: Storage> DataBuffer<S> {
+            $(
+                #[doc = concat!("Read the value written by `", stringify!($put), "()` at the given position without moving the cursors. The program crash if not enough bytes are available")]
+                pub fn $get(&self, pos: usize) -> $ty {
+                    unwrap(self.$try_get(pos))
+                }
+
+                #[doc = concat!("Same as `", stringify!($get), "()`, but return an error if not enough bytes are available")]
+                pub fn $try_get(&self, pos: usize) -> Result<$ty> {
+                    self.check_bounds(pos, $size)?;
+                    let mut bytes = [0; $size];
+                    bytes.copy_from_slice(&self.data[pos..pos + $size]);
+                    let decode: fn([u8; $size]) -> $ty = $decode;
+                    Ok(decode(bytes))
+                }
+            )*
+        }
+    };
+}
+
+absolute_fns! {
+    put_u8_at, try_put_u8_at, get_u8_at, try_get_u8_at => write_u8(u8), 1, |b| b[0];
+    put_i8_at, try_put_i8_at, get_i8_at, try_get_i8_at => write_i8(i8), 1, |b| b[0] as i8;
+    put_u16_at, try_put_u16_at, get_u16_at, try_get_u16_at => write_u16(u16), 2, u16::from_be_bytes;
+    put_i16_at, try_put_i16_at, get_i16_at, try_get_i16_at => write_i16(i16), 2, i16::from_be_bytes;
+    put_u32_at, try_put_u32_at, get_u32_at, try_get_u32_at => write_u32(u32), 4, u32::from_be_bytes;
+    put_i32_at, try_put_i32_at, get_i32_at, try_get_i32_at => write_i32(i32), 4, i32::from_be_bytes;
+    put_u64_at, try_put_u64_at, get_u64_at, try_get_u64_at => write_u64(u64), 8, u64::from_be_bytes;
+    put_i64_at, try_put_i64_at, get_i64_at, try_get_i64_at => write_i64(i64), 8, i64::from_be_bytes;
+    put_f32_at, try_put_f32_at, get_f32_at, try_get_f32_at => write_f32(f32), 4, f32::from_be_bytes;
+    put_f64_at, try_put_f64_at, get_f64_at, try_get_f64_at => write_f64(f64), 8, f64::from_be_bytes;
+    put_medium_at, try_put_medium_at, get_medium_at, try_get_medium_at => write_medium(u32), 3,
+        |b| ((b[0] as u32) << 16) + ((b[1] as u32) << 8) + (b[2] as u32);
+    put_u8_neg_at, try_put_u8_neg_at, get_u8_neg_at, try_get_u8_neg_at => write_u8_neg(u8), 1, |b| b[0].wrapping_neg();
+    put_i8_neg_at, try_put_i8_neg_at, get_i8_neg_at, try_get_i8_neg_at => write_i8_neg(i8), 1, |b| (b[0] as i8).wrapping_neg();
+    put_u8_add_at, try_put_u8_add_at, get_u8_add_at, try_get_u8_add_at => write_u8_add(u8), 1, |b| b[0].wrapping_sub(128);
+    put_i8_add_at, try_put_i8_add_at, get_i8_add_at, try_get_i8_add_at => write_i8_add(i8), 1, |b| b[0].wrapping_sub(128) as i8;
+    put_u8_sub_at, try_put_u8_sub_at, get_u8_sub_at, try_get_u8_sub_at => write_u8_sub(u8), 1, |b| b[0].wrapping_add(128);
+    put_i8_sub_at, try_put_i8_sub_at, get_i8_sub_at, try_get_i8_sub_at => write_i8_sub(i8), 1, |b| b[0].wrapping_add(128) as i8;
+    put_u16_le_at, try_put_u16_le_at, get_u16_le_at, try_get_u16_le_at => write_u16_le(u16), 2, u16::from_le_bytes;
+    put_i16_le_at, try_put_i16_le_at, get_i16_le_at, try_get_i16_le_at => write_i16_le(i16), 2, i16::from_le_bytes;
+    put_u16_le_add_at, try_put_u16_le_add_at, get_u16_le_add_at, try_get_u16_le_add_at => write_u16_le_add(u16), 2,
+        |b| u16::from_le_bytes([b[0].wrapping_sub(128), b[1]]);
+    put_i16_le_add_at, try_put_i16_le_add_at, get_i16_le_add_at, try_get_i16_le_add_at => write_i16_le_add(i16), 2,
+        |b| i16::from_le_bytes([b[0].wrapping_sub(128), b[1]]);
+    put_u32_le_at, try_put_u32_le_at, get_u32_le_at, try_get_u32_le_at => write_u32_le(u32), 4, u32::from_le_bytes;
+    put_i32_le_at, try_put_i32_le_at, get_i32_le_at, try_get_i32_le_at => write_i32_le(i32), 4, i32::from_le_bytes;
+    put_u32_me_at, try_put_u32_me_at, get_u32_me_at, try_get_u32_me_at => write_u32_me(u32), 4,
+        |b| ((b[0] as u32) << 16) + ((b[1] as u32) << 24) + (b[2] as u32) + ((b[3] as u32) << 8);
+    put_u32_me_reversed_at, try_put_u32_me_reversed_at, get_u32_me_reversed_at, try_get_u32_me_reversed_at => write_u32_me_reversed(u32), 4,
+        |b| ((b[0] as u32) << 8) + (b[1] as u32) + ((b[2] as u32) << 24) + ((b[3] as u32) << 16);
+}
+
+/// Generate the `peek_*` and `try_peek_*` counterparts of the fixed-size readers,
+/// which decode the next bytes with the matching `get_*_at` function.
+macro_rules! peek_at_fns {
+    ($($peek:ident, $try_peek:ident => $try_read:ident, $get:ident, $size:expr, $ty:ty;)*) => {
+        impl<S: Storage> DataBuffer<S> {
+            $(
+                #[doc = concat!("Same as `", stringify!($try_read), "()`, but crash on error and do not move the cursors.")]
+                pub fn $peek(&self) -> $ty {
+                    unwrap(self.$try_peek())
+                }
+
+                #[doc = concat!("Same as `", stringify!($try_read), "()`, but do not move the cursors.")]
+                pub fn $try_peek(&self) -> Result<$ty> {
+                    let pos = self.peek_start($size)?;
+                    Ok(self.$get(pos))
+                }
+            )*
+        }
+    };
+}
+
+peek_at_fns! {
+    peek_u8, try_peek_u8 => try_read_u8, get_u8_at, 1, u8;
+    peek_i8, try_peek_i8 => try_read_i8, get_i8_at, 1, i8;
+    peek_u16, try_peek_u16 => try_read_u16, get_u16_at, 2, u16;
+    peek_i16, try_peek_i16 => try_read_i16, get_i16_at, 2, i16;
+    peek_u32, try_peek_u32 => try_read_u32, get_u32_at, 4, u32;
+    peek_i32, try_peek_i32 => try_read_i32, get_i32_at, 4, i32;
+    peek_u64, try_peek_u64 => try_read_u64, get_u64_at, 8, u64;
+    peek_i64, try_peek_i64 => try_read_i64, get_i64_at, 8, i64;
+    peek_f32, try_peek_f32 => try_read_f32, get_f32_at, 4, f32;
+    peek_f64, try_peek_f64 => try_read_f64, get_f64_at, 8, f64;
+    peek_medium, try_peek_medium => try_read_medium, get_medium_at, 3, u32;
+    peek_u8_neg, try_peek_u8_neg => try_read_u8_neg, get_u8_neg_at, 1, u8;
+    peek_i8_neg, try_peek_i8_neg => try_read_i8_neg, get_i8_neg_at, 1, i8;
+    peek_u8_add, try_peek_u8_add => try_read_u8_add, get_u8_add_at, 1, u8;
+    peek_i8_add, try_peek_i8_add => try_read_i8_add, get_i8_add_at, 1, i8;
+    peek_u8_sub, try_peek_u8_sub => try_read_u8_sub, get_u8_sub_at, 1, u8;
+    peek_i8_sub, try_peek_i8_sub => try_read_i8_sub, get_i8_sub_at, 1, i8;
+    peek_u16_le, try_peek_u16_le => try_read_u16_le, get_u16_le_at, 2, u16;
+    peek_i16_le, try_peek_i16_le => try_read_i16_le, get_i16_le_at, 2, i16;
+    peek_u16_le_add, try_peek_u16_le_add => try_read_u16_le_add, get_u16_le_add_at, 2, u16;
+    peek_i16_le_add, try_peek_i16_le_add => try_read_i16_le_add, get_i16_le_add_at, 2, i16;
+    peek_u32_le, try_peek_u32_le => try_read_u32_le, get_u32_le_at, 4, u32;
+    peek_i32_le, try_peek_i32_le => try_read_i32_le, get_i32_le_at, 4, i32;
+    peek_u32_me, try_peek_u32_me => try_read_u32_me, get_u32_me_at, 4, u32;
+    peek_u32_me_reversed, try_peek_u32_me_reversed => try_read_u32_me_reversed, get_u32_me_reversed_at, 4, u32;
+}
+
+/// Generate the `peek_*` and `try_peek_*` counterparts of the other `try_read_*` functions,
+/// which run the reader on a read-only view of the buffer.
+macro_rules! peek_fns {
+    ($($peek:ident, $try_peek:ident => $try_read:ident($($arg:ident: $arg_ty:ty),*) -> $ty:ty;)*) => {
+        impl<S: Storage> DataBuffer<S> {
+            $(
+                #[doc = concat!("Same as `", stringify!($try_read), "()`, but crash on error and do not move the cursors.")]
+                pub fn $peek(&self $(, $arg: $arg_ty)*) -> $ty {
+                    unwrap(self.$try_peek($($arg),*))
+                }
+
+                #[doc = concat!("Same as `", stringify!($try_read), "()`, but do not move the cursors.")]
+                pub fn $try_peek(&self $(, $arg: $arg_ty)*) -> Result<$ty> {
+                    self.view().$try_read($($arg),*)
+                }
+            )*
         }
+    };
+}
+
+peek_fns! {
+    peek_bytes, try_peek_bytes => try_read_bytes(size: usize) -> Vec<u8>;
+    peek_string, try_peek_string => try_read_string() -> String;
+    peek_ntstr, try_peek_ntstr => try_read_ntstr() -> String;
+    peek_dntstr, try_peek_dntstr => try_read_dntstr() -> String;
+    peek_smart, try_peek_smart => try_read_smart() -> u16;
+    peek_var_u32, try_peek_var_u32 => try_read_var_u32() -> u32;
+    peek_var_u64, try_peek_var_u64 => try_read_var_u64() -> u64;
+    peek_var_i32, try_peek_var_i32 => try_read_var_i32() -> i32;
+    peek_var_i64, try_peek_var_i64 => try_read_var_i64() -> i64;
+    peek_var_bytes, try_peek_var_bytes => try_read_var_bytes() -> Vec<u8>;
+    peek_var_str, try_peek_var_str => try_read_var_str() -> String;
+    peek_ntstr_cp1252, try_peek_ntstr_cp1252 => try_read_ntstr_cp1252(policy: Unmappable) -> String;
+    peek_dntstr_cp1252, try_peek_dntstr_cp1252 => try_read_dntstr_cp1252(policy: Unmappable) -> String;
+    peek_str_cp1252, try_peek_str_cp1252 => try_read_str_cp1252(kind: LengthKind, policy: Unmappable) -> String;
+    peek_java_utf, try_peek_java_utf => try_read_java_utf() -> String;
+    peek_length, try_peek_length => try_read_length(kind: LengthKind) -> usize;
+    peek_bytes_prefixed, try_peek_bytes_prefixed => try_read_bytes_prefixed(kind: LengthKind, max_len: usize) -> Vec<u8>;
+    peek_string_prefixed, try_peek_string_prefixed => try_read_string_prefixed(kind: LengthKind, max_len: usize) -> String;
+    peek_string_lossy, try_peek_string_lossy => try_read_string_lossy() -> String;
+    peek_string_prefixed_lossy, try_peek_string_prefixed_lossy => try_read_string_prefixed_lossy(kind: LengthKind, max_len: usize) -> String;
+    peek_bit, try_peek_bit => try_read_bit() -> bool;
+    peek_bits, try_peek_bits => try_read_bits(n: u8) -> u64;
+    peek_bits_signed, try_peek_bits_signed => try_read_bits_signed(n: u8) -> i64;
+}
+
+impl Default for DataBuffer {
+    fn default() -> DataBuffer {
+        DataBuffer::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Storage> DataBuffer<S> {
+    /// Return the content of the buffer as an `IoSlice`, for vectored writes
+    pub fn as_io_slice(&self) -> IoSlice<'_> {
+        IoSlice::new(&self.data)
+    }
+
+    /// Gather the content of several buffers, such as finished packets, into `IoSlice`s,
+    /// so they can be sent with a single vectored write without being concatenated.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// #  use std::io::Write;
+    /// let mut packets = vec![DataBuffer::create(1, PacketHeader::NORMAL), DataBuffer::create(2, PacketHeader::NORMAL)];
+    /// packets[1].write_u16(3);
+    /// let mut socket = Vec::new();
+    /// let written = socket.write_vectored(&DataBuffer::io_slices(&packets)).unwrap();
+    /// assert_eq!(written, 4);
+    /// assert_eq!(socket, vec![0x01, 0x02, 0x00, 0x03]);
+    /// ```
+    pub fn io_slices<'a, I>(buffers: I) -> Vec<IoSlice<'a>>
+        where I: IntoIterator<Item = &'a DataBuffer<S>>, S: 'a
+    {
+        buffers.into_iter().map(DataBuffer::as_io_slice).collect()
     }
 }
 
-impl Read for DataBuffer {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+#[cfg(feature = "std")]
+impl<S: StorageMut> DataBuffer<S> {
+    /// Move the writing cursor, like `Seek::seek()` moves the reading cursor.
+    /// The pending bits are discarded, and the cursor is kept inside the buffer.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// #  use std::io::SeekFrom;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x1, 0x2, 0x3]);
+    /// assert_eq!(buffer.seek_write(SeekFrom::End(-2)).unwrap(), 1);
+    /// buffer.write_u8(0x4); // buffer contains [0x1, 0x4, 0x3]
+    /// ```
+    pub fn seek_write(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let wpos = seek_position(pos, self.wpos, self.data.len())?;
+        self.wpos = wpos;
+        self.wbit = 0;
+        Ok(wpos as u64)
+    }
+}
+
+/// Resolve a seek from the given cursor, keeping the result inside the buffer
+#[cfg(feature = "std")]
+fn seek_position(pos: SeekFrom, current: usize, len: usize) -> io::Result<usize> {
+    let (base, offset) = match pos {
+        SeekFrom::Start(offset) => return Ok(core::cmp::min(offset, len as u64) as usize),
+        SeekFrom::End(offset) => (len, offset),
+        SeekFrom::Current(offset) => (current, offset)
+    };
+    let pos = base as i128 + offset as i128;
+    if pos < 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot seek before the start of the buffer"));
+    }
+    Ok(core::cmp::min(pos, len as i128) as usize)
+}
+
+#[cfg(feature = "std")]
+impl<S: Storage> Read for DataBuffer<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.flush_bit();
-        let read_len = std::cmp::min(self.data.len() - self.rpos, buf.len());
+        let read_len = core::cmp::min(self.data.len() - self.rpos, buf.len());
         let range = self.rpos..self.rpos + read_len;
-        for (i, val) in (&self.data[range]).iter().enumerate() {
+        for (i, val) in self.data[range].iter().enumerate() {
             buf[i] = *val;
         }
         self.rpos += read_len;
@@ -830,19 +2621,90 @@ impl Read for DataBuffer {
     }
 }
 
-impl Write for DataBuffer {
-    fn write(&mut self, buf: &[u8]) -> Result<usize> {
-        self.write_bytes(buf);
-        Ok(buf.len())
+/// Move the reading cursor. The pending bits are discarded, and the cursor is kept
+/// inside the buffer: seeking past the end moves it to the end.
+#[cfg(feature = "std")]
+impl<S: Storage> Seek for DataBuffer<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let rpos = seek_position(pos, self.remaining_start(), self.data.len())?;
+        self.rpos = rpos;
+        self.rbit = 0;
+        Ok(rpos as u64)
+    }
+}
+
+/// `fill_buf()` returns the unread bytes without copying them.
+#[cfg(feature = "std")]
+impl<S: Storage> BufRead for DataBuffer<S> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let start = core::cmp::min(self.remaining_start(), self.data.len());
+        Ok(&self.data[start..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.rpos = core::cmp::min(self.remaining_start() + amt, self.data.len());
+        self.rbit = 0;
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: StorageMut> Write for DataBuffer<S> {
+    /// Write the bytes, or with a fixed-capacity storage, as many of them as fit.
+    /// `Ok(0)` is returned once the storage is full.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = match self.try_write_bytes(buf) {
+            Ok(()) => return Ok(buf.len()),
+            Err(Error::CapacityExceeded { capacity, .. }) => {
+                let start = if self.wbit > 0 { self.wpos + 1 } else { self.wpos };
+                core::cmp::min(capacity.saturating_sub(start), buf.len())
+            }
+            Err(err) => return Err(io::Error::other(err))
+        };
+        if len > 0 {
+            self.try_write_bytes(&buf[..len]).map_err(io::Error::other)?;
+        }
+        Ok(len)
+    }
+
+    /// Write every slice, growing the buffer once for all of them.
+    /// With a fixed-capacity storage, the slices are written until one does not fit,
+    /// including the part of it that fits.
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let total = bufs.iter().map(|buf| buf.len()).sum();
+        self.reserve(total);
+
+        let mut written = 0;
+        for buf in bufs {
+            let len = self.write(buf)?;
+            written += len;
+            if len < buf.len() {
+                break;
+            }
+        }
+        Ok(written)
     }
 
-    fn flush(&mut self) -> Result<()> {
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Dump the byte buffer as a list of hexadecimal bytes, such as `0x00 0x01`.
+/// See [`DataBuffer::hexdump`] for a more readable dump.
+impl<S: Storage> core::fmt::Display for DataBuffer<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for (i, b) in self.data.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "0x{:02x}", b)?;
+        }
         Ok(())
     }
 }
 
-impl std::fmt::Debug for DataBuffer {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl<S: Storage> core::fmt::Debug for DataBuffer<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         let rpos = if self.rbit > 0 {
             self.rpos + 1
         } else {
@@ -852,11 +2714,11 @@ impl std::fmt::Debug for DataBuffer {
         let read_len = self.data.len() - rpos;
         let mut remaining_data = vec![0; read_len];
         let range = rpos..rpos + read_len;
-        for (i, val) in (&self.data[range]).iter().enumerate() {
+        for (i, val) in self.data[range].iter().enumerate() {
             remaining_data[i] = *val;
         }
 
         write!(f, "DataBuffer {{ remaining_data: {:?}, total_data: {:?} }}",
-               remaining_data, self.data)
+               remaining_data, &self.data[..])
     }
 }
@@ -0,0 +1,296 @@
+//! The bit-packed movement section of RuneScape player and NPC updating.
+//!
+//! Each client keeps a list of the entities around it, the local list. Every tick, the
+//! server sends the movement of the local player, then the movement or removal of each
+//! entity of the previous local list, in the same order, then the entities added to the
+//! list, and finally a terminator, an index with every bit set.
+//!
+//! #Example
+//!
+//! ```
+//! #  use databuffer::*;
+//! #  use databuffer::movement::*;
+//! let viewer = (3222, 3222);
+//! let current = [
+//!     LocalEntity::new(7, 3223, 3222).with_movement(Movement::Walk(4)),
+//!     LocalEntity::new(12, 3220, 3225).with_update(true)
+//! ];
+//! let mut buffer = DataBuffer::new();
+//! buffer.write_local_movement(Movement::Stay, false);
+//! let list = buffer.write_entity_list(ListFormat::players(), viewer, &[7, 9], &current);
+//! assert_eq!(list, vec![7, 12]);
+//! ```
+
+use alloc::vec::Vec;
+
+use {DataBuffer, Error, Result, StorageMut};
+
+/// Return the direction code of a one-tile step, from 0 for north-west to 7 for south-east,
+/// or `None` if the step is not a single tile.
+///
+/// #Example
+///
+/// ```
+/// # use databuffer::movement;
+/// assert_eq!(movement::direction(-1, 1), Some(0));
+/// assert_eq!(movement::direction(1, 0), Some(4));
+/// assert_eq!(movement::direction(2, 0), None);
+/// ```
+pub fn direction(dx: i32, dy: i32) -> Option<u8> {
+    match (dx, dy) {
+        (-1, 1) => Some(0),
+        (0, 1) => Some(1),
+        (1, 1) => Some(2),
+        (-1, 0) => Some(3),
+        (1, 0) => Some(4),
+        (-1, -1) => Some(5),
+        (0, -1) => Some(6),
+        (1, -1) => Some(7),
+        _ => None
+    }
+}
+
+/// The movement of an entity during the tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Movement {
+    /// The entity did not move.
+    Stay,
+    /// The entity walked one tile in the given direction, see [`direction`].
+    Walk(u8),
+    /// The entity ran two tiles, in the given directions.
+    Run(u8, u8),
+    /// The entity was moved to the given position, local to the loaded region.
+    /// Only the local player can teleport: the other entities are removed from the
+    /// local list instead, and added back on the next tick.
+    Teleport { plane: u8, x: u8, y: u8, discard_queue: bool }
+}
+
+/// An entity visible by the local player this tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalEntity {
+    /// The index of the entity in the world
+    pub index: u16,
+    /// The type of the NPC, unused for players
+    pub id: u16,
+    /// The absolute position of the entity
+    pub x: u16,
+    /// The absolute position of the entity
+    pub y: u16,
+    /// The movement of the entity during the tick
+    pub movement: Movement,
+    /// True if update blocks follow for this entity
+    pub update: bool
+}
+
+impl LocalEntity {
+    /// Construct an entity at the given position, which did not move and has no update blocks
+    pub fn new(index: u16, x: u16, y: u16) -> LocalEntity {
+        LocalEntity { index, id: 0, x, y, movement: Movement::Stay, update: false }
+    }
+
+    /// Set the type of the NPC
+    pub fn with_id(mut self, id: u16) -> LocalEntity {
+        self.id = id;
+        self
+    }
+
+    /// Set the movement of the entity during the tick
+    pub fn with_movement(mut self, movement: Movement) -> LocalEntity {
+        self.movement = movement;
+        self
+    }
+
+    /// Set whether update blocks follow for this entity
+    pub fn with_update(mut self, update: bool) -> LocalEntity {
+        self.update = update;
+        self
+    }
+}
+
+/// The layout of a local list, which differs between players and NPCs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListFormat {
+    npcs: bool,
+    index_bits: u8,
+    id_bits: u8
+}
+
+impl ListFormat {
+    /// The local players, with 11-bit indexes, so a terminator of 2047.
+    /// Additions are written as the index, the update flag, the discard-queue flag,
+    /// then the 5-bit y and x deltas.
+    pub fn players() -> ListFormat {
+        ListFormat { npcs: false, index_bits: 11, id_bits: 0 }
+    }
+
+    /// The local NPCs, with 15-bit indexes, so a terminator of 32767, and 14-bit types.
+    /// Additions are written as the index, the 5-bit y and x deltas, the discard-queue
+    /// flag, the type, then the update flag.
+    pub fn npcs() -> ListFormat {
+        ListFormat { npcs: true, index_bits: 15, id_bits: 14 }
+    }
+
+    /// Set the number of bits of the indexes, and so the terminator.
+    /// The program crash if the number of bits is not between 1 and 32.
+    pub fn with_index_bits(mut self, bits: u8) -> ListFormat {
+        assert!((1..=32).contains(&bits), "the indexes must use between 1 and 32 bits");
+        self.index_bits = bits;
+        self
+    }
+
+    /// Set the number of bits of the NPC types.
+    /// The program crash if the number of bits is not between 1 and 32.
+    pub fn with_id_bits(mut self, bits: u8) -> ListFormat {
+        assert!((1..=32).contains(&bits), "the types must use between 1 and 32 bits");
+        self.id_bits = bits;
+        self
+    }
+
+    /// Return the index which ends the additions, every bit of the index set
+    pub fn terminator(&self) -> u64 {
+        max_value(self.index_bits)
+    }
+}
+
+/// Return the largest value which fits in the given number of bits, up to 64
+fn max_value(bits: u8) -> u64 {
+    u64::MAX.checked_shr(64 - bits as u32).unwrap_or(0)
+}
+
+/// Check that a value fits in the given number of bits
+fn check_bits(value: u64, bits: u8) -> Result<u64> {
+    let max = max_value(bits);
+    if value > max {
+        return Err(Error::ValueOutOfRange { value, max });
+    }
+    Ok(value)
+}
+
+/// Return the delta of an added entity from the viewer, which must fit in 5 signed bits
+fn delta(from: u16, to: u16) -> Result<i64> {
+    let delta = to as i64 - from as i64;
+    if !(-16..=15).contains(&delta) {
+        return Err(Error::ValueOutOfRange { value: delta.unsigned_abs(), max: 15 });
+    }
+    Ok(delta)
+}
+
+impl<S: StorageMut> DataBuffer<S> {
+    /// Write the movement of the local player, which comes first in player updating.
+    /// The program crash if a direction or a position is out of range.
+    pub fn write_local_movement(&mut self, movement: Movement, update: bool) {
+        ::unwrap(self.try_write_local_movement(movement, update))
+    }
+
+    /// Same as `write_local_movement()`, but return an error if a direction or a position
+    /// is out of range. Nothing is written on error.
+    pub fn try_write_local_movement(&mut self, movement: Movement, update: bool) -> Result<()> {
+        self.trace_write_bits("write_local_movement", &movement, |buf| {
+            match movement {
+                Movement::Teleport { plane, x, y, discard_queue } => {
+                    buf.try_write_bits(1, 1)?;
+                    buf.try_write_bits(3, 2)?;
+                    buf.try_write_bits(check_bits(plane as u64, 2)?, 2)?;
+                    buf.try_write_bit(discard_queue)?;
+                    buf.try_write_bit(update)?;
+                    buf.try_write_bits(check_bits(y as u64, 7)?, 7)?;
+                    buf.try_write_bits(check_bits(x as u64, 7)?, 7)
+                }
+                _ => buf.try_write_step(movement, update)
+            }
+        })
+    }
+
+    /// Write the movement section of a local list: the movement or removal of every entity
+    /// of the previous list, then the entities added to it, then the terminator.
+    /// Return the indexes of the new local list, to pass as `previous` on the next tick.
+    ///
+    /// The entities of the previous list missing from `current`, or which teleported, are
+    /// removed. The entities of `current` missing from the previous list are added, in the
+    /// given order, with their position relative to the viewer.
+    ///
+    /// The program crash if the previous list holds more than 255 entities, or if a value
+    /// does not fit in its field, such as an added entity further than 15 tiles away.
+    pub fn write_entity_list(&mut self, format: ListFormat, viewer: (u16, u16), previous: &[u16],
+                             current: &[LocalEntity]) -> Vec<u16> {
+        ::unwrap(self.try_write_entity_list(format, viewer, previous, current))
+    }
+
+    /// Same as `write_entity_list()`, but return an error if a value does not fit in its
+    /// field. Nothing is written on error.
+    pub fn try_write_entity_list(&mut self, format: ListFormat, viewer: (u16, u16), previous: &[u16],
+                                 current: &[LocalEntity]) -> Result<Vec<u16>> {
+        let mut list = Vec::with_capacity(current.len());
+        self.trace_write_bits("write_entity_list", current, |buf| {
+            buf.try_write_bits(check_bits(previous.len() as u64, 8)?, 8)?;
+            for &index in previous {
+                match current.iter().find(|entity| entity.index == index) {
+                    Some(entity) if !matches!(entity.movement, Movement::Teleport { .. }) => {
+                        buf.try_write_step(entity.movement, entity.update)?;
+                        list.push(index);
+                    }
+                    _ => {
+                        buf.try_write_bits(1, 1)?;
+                        buf.try_write_bits(3, 2)?;
+                    }
+                }
+            }
+
+            for entity in current.iter().filter(|entity| !previous.contains(&entity.index)) {
+                buf.try_write_addition(format, viewer, entity)?;
+                list.push(entity.index);
+            }
+            buf.try_write_bits(format.terminator(), format.index_bits)
+        })?;
+        Ok(list)
+    }
+
+    /// Write the movement of an entity which stayed, walked or ran
+    fn try_write_step(&mut self, movement: Movement, update: bool) -> Result<()> {
+        match movement {
+            Movement::Stay if !update => self.try_write_bit(false),
+            Movement::Stay => {
+                self.try_write_bit(true)?;
+                self.try_write_bits(0, 2)
+            }
+            Movement::Walk(direction) => {
+                self.try_write_bit(true)?;
+                self.try_write_bits(1, 2)?;
+                self.try_write_bits(check_bits(direction as u64, 3)?, 3)?;
+                self.try_write_bit(update)
+            }
+            Movement::Run(first, second) => {
+                self.try_write_bit(true)?;
+                self.try_write_bits(2, 2)?;
+                self.try_write_bits(check_bits(first as u64, 3)?, 3)?;
+                self.try_write_bits(check_bits(second as u64, 3)?, 3)?;
+                self.try_write_bit(update)
+            }
+            Movement::Teleport { .. } => unreachable!("teleports are handled by the callers")
+        }
+    }
+
+    /// Write an entity added to the local list
+    fn try_write_addition(&mut self, format: ListFormat, viewer: (u16, u16), entity: &LocalEntity) -> Result<()> {
+        let index = check_bits(entity.index as u64, format.index_bits)?;
+        if index == format.terminator() {
+            return Err(Error::ValueOutOfRange { value: index, max: format.terminator() - 1 });
+        }
+        let dx = delta(viewer.0, entity.x)?;
+        let dy = delta(viewer.1, entity.y)?;
+
+        self.try_write_bits(index, format.index_bits)?;
+        if format.npcs {
+            self.try_write_bits_signed(dy, 5)?;
+            self.try_write_bits_signed(dx, 5)?;
+            self.try_write_bit(true)?;
+            self.try_write_bits(check_bits(entity.id as u64, format.id_bits)?, format.id_bits)?;
+            self.try_write_bit(entity.update)
+        } else {
+            self.try_write_bit(entity.update)?;
+            self.try_write_bit(true)?;
+            self.try_write_bits_signed(dy, 5)?;
+            self.try_write_bits_signed(dx, 5)
+        }
+    }
+}
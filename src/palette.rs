@@ -0,0 +1,209 @@
+//! The chunk palette of dynamic regions, such as instances.
+//!
+//! A dynamic region is built from chunks copied from anywhere in the map. The palette
+//! tells, for each of the 4×13×13 chunks of the area loaded by the client, which chunk of
+//! the map to copy and how to rotate it. It is written as a presence bit per entry, followed
+//! for present entries by 26 bits holding the plane, x, y and rotation of the source chunk.
+//! The XTEA keys of every region the chunks are copied from follow, in the order the
+//! regions are first referenced.
+//!
+//! #Example
+//!
+//! ```
+//! #  use databuffer::*;
+//! #  use databuffer::palette::*;
+//! let mut palette = Palette::new();
+//! palette.set(0, 6, 6, PaletteChunk::new(Coordinate::new(1864, 5056, 0), 1));
+//! palette.set_keys(7503, [1, 2, 3, 4]);
+//!
+//! let mut buffer = DataBuffer::new();
+//! buffer.write_palette(&palette);
+//! assert_eq!(buffer.read_palette(), palette);
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use {Coordinate, DataBuffer, Error, Result, Storage, StorageMut};
+
+/// The number of planes of the palette
+pub const PLANES: usize = 4;
+
+/// The number of chunks of the palette along each axis
+pub const SIZE: usize = 13;
+
+/// A chunk of the map, copied into a dynamic region with the given rotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaletteChunk {
+    coord: Coordinate,
+    rotation: u8
+}
+
+impl PaletteChunk {
+    /// Construct an entry copying the chunk holding the given tile, rotated clockwise by
+    /// `rotation` quarter turns
+    pub fn new(coord: Coordinate, rotation: u8) -> PaletteChunk {
+        PaletteChunk { coord: Coordinate::new(coord.x & !0x7, coord.y & !0x7, coord.plane), rotation }
+    }
+
+    /// Return the south-west tile of the copied chunk
+    pub fn coord(&self) -> Coordinate {
+        self.coord
+    }
+
+    /// Return the number of clockwise quarter turns applied to the chunk
+    pub fn rotation(&self) -> u8 {
+        self.rotation
+    }
+
+    /// Return the entry packed on 26 bits, as `plane << 24 | chunk_x << 14 | chunk_y << 3 | rotation << 1`.
+    /// An error is returned if a field does not fit.
+    pub fn packed(&self) -> Result<u32> {
+        let fields = [
+            (self.coord.plane as u64, 0x3),
+            (self.coord.chunk_x() as u64, 0x3FF),
+            (self.coord.chunk_y() as u64, 0x7FF),
+            (self.rotation as u64, 0x3)
+        ];
+        if let Some(&(value, max)) = fields.iter().find(|&&(value, max)| value > max) {
+            return Err(Error::ValueOutOfRange { value, max });
+        }
+        Ok((self.coord.plane as u32) << 24 | (self.coord.chunk_x() as u32) << 14
+            | (self.coord.chunk_y() as u32) << 3 | (self.rotation as u32) << 1)
+    }
+
+    /// Unpack an entry packed with `packed()`
+    pub fn from_packed(packed: u32) -> PaletteChunk {
+        let coord = Coordinate::new(((packed >> 14 & 0x3FF) << 3) as u16, ((packed >> 3 & 0x7FF) << 3) as u16,
+                                    (packed >> 24 & 0x3) as u8);
+        PaletteChunk { coord, rotation: (packed >> 1 & 0x3) as u8 }
+    }
+}
+
+/// The chunks of a dynamic region, with the keys of the regions they are copied from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    chunks: Vec<Option<PaletteChunk>>,
+    keys: BTreeMap<u16, [i32; 4]>
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::new()
+    }
+}
+
+/// Return the position of an entry in the palette.
+/// The program crash if the entry is outside the palette.
+fn entry(plane: usize, x: usize, y: usize) -> usize {
+    assert!(plane < PLANES && x < SIZE && y < SIZE, "the entry ({}, {}, {}) is outside the palette", plane, x, y);
+    (plane * SIZE + x) * SIZE + y
+}
+
+impl Palette {
+    /// Construct an empty palette
+    pub fn new() -> Palette {
+        Palette { chunks: vec![None; PLANES * SIZE * SIZE], keys: BTreeMap::new() }
+    }
+
+    /// Copy the given chunk at the given entry, replacing the previous one if any.
+    /// The program crash if the entry is outside the palette.
+    pub fn set(&mut self, plane: usize, x: usize, y: usize, chunk: PaletteChunk) -> Option<PaletteChunk> {
+        self.chunks[entry(plane, x, y)].replace(chunk)
+    }
+
+    /// Return the chunk copied at the given entry, if any
+    pub fn get(&self, plane: usize, x: usize, y: usize) -> Option<&PaletteChunk> {
+        self.chunks[entry(plane, x, y)].as_ref()
+    }
+
+    /// Empty the given entry, returning its chunk if any
+    pub fn remove(&mut self, plane: usize, x: usize, y: usize) -> Option<PaletteChunk> {
+        self.chunks[entry(plane, x, y)].take()
+    }
+
+    /// Set the XTEA keys of a region. Zeros, the default, are used for unencrypted regions.
+    pub fn set_keys(&mut self, region_id: u16, keys: [i32; 4]) {
+        if keys == [0; 4] {
+            self.keys.remove(&region_id);
+        } else {
+            self.keys.insert(region_id, keys);
+        }
+    }
+
+    /// Return the XTEA keys of a region, zeros if they were not set
+    pub fn keys(&self, region_id: u16) -> [i32; 4] {
+        self.keys.get(&region_id).cloned().unwrap_or([0; 4])
+    }
+
+    /// Return the ids of the regions the chunks are copied from, in the order they are first referenced
+    pub fn regions(&self) -> Vec<u16> {
+        let mut regions = Vec::new();
+        for chunk in self.chunks.iter().flatten() {
+            let region = chunk.coord.region_id();
+            if !regions.contains(&region) {
+                regions.push(region);
+            }
+        }
+        regions
+    }
+}
+
+impl<S: StorageMut> DataBuffer<S> {
+    /// Write the palette of a dynamic region: a presence bit and 26 bits per present entry,
+    /// for every plane, then x, then y, followed by the keys of every referenced region.
+    /// The program crash if a chunk cannot be packed.
+    pub fn write_palette(&mut self, palette: &Palette) {
+        ::unwrap(self.try_write_palette(palette))
+    }
+
+    /// Same as `write_palette()`, but return an error if a chunk cannot be packed.
+    /// Nothing is written on error.
+    pub fn try_write_palette(&mut self, palette: &Palette) -> Result<()> {
+        self.trace_write_bits("write_palette", palette, |buf| {
+            for chunk in &palette.chunks {
+                match *chunk {
+                    Some(chunk) => {
+                        buf.try_write_bit(true)?;
+                        buf.try_write_bits(chunk.packed()? as u64, 26)?;
+                    }
+                    None => buf.try_write_bit(false)?
+                }
+            }
+            for region in palette.regions() {
+                for &key in &palette.keys(region) {
+                    buf.try_write_i32(key)?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<S: Storage> DataBuffer<S> {
+    /// Read the palette of a dynamic region written with `write_palette()`
+    pub fn read_palette(&mut self) -> Palette {
+        ::unwrap(self.try_read_palette())
+    }
+
+    /// Same as `read_palette()`, but return an error if not enough bytes are available.
+    /// The cursor is left untouched on error.
+    pub fn try_read_palette(&mut self) -> Result<Palette> {
+        self.trace_read_bits("read_palette", |buf| buf.transaction(|buf| {
+            let mut palette = Palette::new();
+            for chunk in &mut palette.chunks {
+                if buf.try_read_bit()? {
+                    *chunk = Some(PaletteChunk::from_packed(buf.try_read_bits(26)? as u32));
+                }
+            }
+            for region in palette.regions() {
+                let mut keys = [0; 4];
+                for key in &mut keys {
+                    *key = buf.try_read_i32()?;
+                }
+                palette.set_keys(region, keys);
+            }
+            Ok(palette)
+        }))
+    }
+}
@@ -0,0 +1,176 @@
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::ops::{Deref, DerefMut};
+
+use {DataBuffer, PacketHeader};
+
+/// A pool of buffers, to reuse their allocations instead of creating and dropping
+/// short-lived buffers, such as the packets built on every game tick.
+///
+/// Buffers are handed out as [`PooledBuffer`] guards, which return them to the pool,
+/// cleared but with their capacity retained, when dropped. The pool is meant to be owned
+/// by a single thread, such as the one running the game loop.
+///
+/// #Example
+///
+/// ```
+/// #  use databuffer::*;
+/// let pool = DataBufferPool::new(16);
+/// {
+///     let mut buffer = pool.create(5, PacketHeader::BYTE);
+///     buffer.write_u16(1);
+///     buffer.finish();
+///     assert_eq!(buffer.to_bytes(), vec![0x05, 0x02, 0x00, 0x01]);
+/// }
+/// let buffer = pool.get();
+/// assert!(buffer.is_empty());
+/// assert_eq!(pool.stats().hits(), 1);
+/// assert_eq!(pool.stats().misses(), 1);
+/// ```
+pub struct DataBufferPool {
+    buffers: RefCell<Vec<DataBuffer>>,
+    max_pooled: usize,
+    max_capacity: usize,
+    hits: Cell<u64>,
+    misses: Cell<u64>
+}
+
+/// The statistics of a [`DataBufferPool`], see [`DataBufferPool::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+    hits: u64,
+    misses: u64,
+    pooled: usize,
+    bytes_retained: usize
+}
+
+impl PoolStats {
+    /// Return the number of buffers handed out by reusing a pooled buffer
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Return the number of buffers handed out by allocating a new buffer
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Return the number of buffers currently in the pool
+    pub fn pooled(&self) -> usize {
+        self.pooled
+    }
+
+    /// Return the total capacity of the buffers currently in the pool, in bytes
+    pub fn bytes_retained(&self) -> usize {
+        self.bytes_retained
+    }
+}
+
+impl DataBufferPool {
+    /// Construct an empty pool, keeping at most `max_pooled` buffers.
+    /// Buffers returned to a full pool are dropped.
+    pub fn new(max_pooled: usize) -> DataBufferPool {
+        DataBufferPool {
+            buffers: RefCell::new(Vec::with_capacity(max_pooled)),
+            max_pooled,
+            max_capacity: usize::MAX,
+            hits: Cell::new(0),
+            misses: Cell::new(0)
+        }
+    }
+
+    /// Drop the returned buffers whose capacity is larger than `max_capacity` bytes,
+    /// instead of keeping them in the pool. Unlimited by default.
+    pub fn with_max_capacity(mut self, max_capacity: usize) -> DataBufferPool {
+        self.max_capacity = max_capacity;
+        self
+    }
+
+    /// Return an empty buffer, reusing a pooled buffer if any
+    pub fn get(&self) -> PooledBuffer<'_> {
+        let buffer = match self.buffers.borrow_mut().pop() {
+            Some(buffer) => {
+                self.hits.set(self.hits.get() + 1);
+                buffer
+            }
+            None => {
+                self.misses.set(self.misses.get() + 1);
+                DataBuffer::new()
+            }
+        };
+        PooledBuffer { pool: self, buffer: Some(buffer) }
+    }
+
+    /// Same as `DataBuffer::create()`, but reusing a pooled buffer if any
+    pub fn create(&self, opcode: u8, header: PacketHeader) -> PooledBuffer<'_> {
+        let mut buffer = self.get();
+        buffer.start_packet(opcode, header);
+        buffer
+    }
+
+    /// Return the statistics of the pool
+    pub fn stats(&self) -> PoolStats {
+        let buffers = self.buffers.borrow();
+        PoolStats {
+            hits: self.hits.get(),
+            misses: self.misses.get(),
+            pooled: buffers.len(),
+            bytes_retained: buffers.iter().map(DataBuffer::capacity).sum()
+        }
+    }
+
+    /// Drop every pooled buffer, releasing their memory. The statistics are kept.
+    pub fn shrink(&self) {
+        self.buffers.borrow_mut().clear();
+    }
+
+    /// Reset a buffer and put it back in the pool, unless the pool is full
+    fn recycle(&self, mut buffer: DataBuffer) {
+        if buffer.capacity() > self.max_capacity {
+            return;
+        }
+        let mut buffers = self.buffers.borrow_mut();
+        if buffers.len() < self.max_pooled {
+            buffer.clear();
+            buffer.disable_trace();
+            buffers.push(buffer);
+        }
+    }
+}
+
+/// A buffer borrowed from a [`DataBufferPool`], returned to the pool when dropped.
+///
+/// The guard dereferences to the buffer, so it can be used like the buffer itself.
+pub struct PooledBuffer<'a> {
+    pool: &'a DataBufferPool,
+    buffer: Option<DataBuffer>
+}
+
+impl<'a> PooledBuffer<'a> {
+    /// Take the buffer out of the pool for good, it will not be returned when dropped
+    pub fn detach(mut self) -> DataBuffer {
+        self.buffer.take().expect("the buffer is only taken when the guard is consumed")
+    }
+}
+
+impl<'a> Deref for PooledBuffer<'a> {
+    type Target = DataBuffer;
+
+    fn deref(&self) -> &DataBuffer {
+        self.buffer.as_ref().expect("the buffer is only taken when the guard is consumed")
+    }
+}
+
+impl<'a> DerefMut for PooledBuffer<'a> {
+    fn deref_mut(&mut self) -> &mut DataBuffer {
+        self.buffer.as_mut().expect("the buffer is only taken when the guard is consumed")
+    }
+}
+
+impl<'a> Drop for PooledBuffer<'a> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            self.pool.recycle(buffer);
+        }
+    }
+}
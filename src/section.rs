@@ -0,0 +1,208 @@
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+
+use {DataBuffer, Error, Result, StorageMut};
+
+/// The encoding of a length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthKind {
+    /// A single byte.
+    U8,
+    /// A single byte, written with `write_u8_neg()`.
+    U8Neg,
+    /// A single byte, written with `write_u8_add()`.
+    U8Add,
+    /// A big-endian word.
+    U16,
+    /// A big-endian double word.
+    U32,
+    /// A smart, see `write_smart()`.
+    Smart,
+    /// An unsigned LEB128 variable-length integer.
+    VarInt
+}
+
+impl LengthKind {
+    /// Return the largest length this prefix can hold
+    pub fn max_length(&self) -> u64 {
+        match *self {
+            LengthKind::U8 | LengthKind::U8Neg | LengthKind::U8Add => u8::MAX as u64,
+            LengthKind::U16 => u16::MAX as u64,
+            LengthKind::U32 | LengthKind::VarInt => u32::MAX as u64,
+            LengthKind::Smart => 32767
+        }
+    }
+
+    /// Return the size of the prefix in bytes, or `None` if it depends on the length
+    pub fn fixed_size(&self) -> Option<usize> {
+        match *self {
+            LengthKind::U8 | LengthKind::U8Neg | LengthKind::U8Add => Some(1),
+            LengthKind::U16 => Some(2),
+            LengthKind::U32 => Some(4),
+            LengthKind::Smart | LengthKind::VarInt => None
+        }
+    }
+
+    /// Encode the given length into `out` and return the number of bytes used.
+    pub(crate) fn encode(&self, length: usize, out: &mut [u8; 10]) -> Result<usize> {
+        let value = length as u64;
+        if value > self.max_length() {
+            return Err(Error::ValueOutOfRange { value, max: self.max_length() });
+        }
+
+        let size = match *self {
+            LengthKind::U8 => {
+                out[0] = value as u8;
+                1
+            }
+            LengthKind::U8Neg => {
+                out[0] = (value as u8).wrapping_neg();
+                1
+            }
+            LengthKind::U8Add => {
+                out[0] = (value as u8).wrapping_add(128);
+                1
+            }
+            LengthKind::U16 => {
+                out[..2].copy_from_slice(&(value as u16).to_be_bytes());
+                2
+            }
+            LengthKind::U32 => {
+                out[..4].copy_from_slice(&(value as u32).to_be_bytes());
+                4
+            }
+            LengthKind::Smart => {
+                if value >= 128 {
+                    out[..2].copy_from_slice(&(value as u16 + 32768).to_be_bytes());
+                    2
+                } else {
+                    out[0] = value as u8;
+                    1
+                }
+            }
+            LengthKind::VarInt => ::encode_var_u64(value, out)
+        };
+
+        Ok(size)
+    }
+}
+
+/// A length-prefixed section of a [`DataBuffer`], created by [`DataBuffer::begin_section`].
+///
+/// The section dereferences to the buffer it was created from, so it can be written to
+/// directly, and new sections can be nested inside it. The length prefix is written when
+/// the section is closed with [`close`](#method.close) or dropped.
+///
+/// The length is the distance from the start of the body to the write cursor, so the
+/// cursor must not be moved before the body while the section is open, such as with
+/// `set_wpos()`, `clear()` or `compact()`. Closing the section fails if it was.
+pub struct Section<'a, S: StorageMut = Vec<u8>> {
+    buffer: &'a mut DataBuffer<S>,
+    kind: LengthKind,
+    start: usize,
+    body_start: usize,
+    closed: bool
+}
+
+impl<'a, S: StorageMut> Section<'a, S> {
+    pub(crate) fn new(buffer: &'a mut DataBuffer<S>, kind: LengthKind) -> Result<Section<'a, S>> {
+        buffer.flush_bit();
+        let start = buffer.wpos;
+        if let Some(size) = kind.fixed_size() {
+            buffer.try_write_bytes(&[0; 4][..size])?;
+        }
+        let body_start = buffer.wpos;
+
+        Ok(Section { buffer, kind, start, body_start, closed: false })
+    }
+
+    /// Return the number of bytes written in the section so far, excluding the length prefix
+    pub fn length(&self) -> usize {
+        let wpos = if self.buffer.wbit > 0 { self.buffer.wpos + 1 } else { self.buffer.wpos };
+        wpos.saturating_sub(self.body_start)
+    }
+
+    /// Close the section, writing its length prefix, and return the length of the section.
+    /// An error is returned if the length does not fit in the prefix, in which case the
+    /// prefix is left as zeros, if the write cursor was moved before the body, or if the
+    /// buffer cannot hold a variable-length prefix.
+    pub fn close(mut self) -> Result<usize> {
+        self.closed = true;
+        self.write_length(None)
+    }
+
+    /// Close the section, writing the given value as its prefix instead of its length,
+    /// such as the number of elements it holds
+    #[cfg(feature = "serde")]
+    pub(crate) fn close_with(mut self, value: usize) -> Result<usize> {
+        self.closed = true;
+        self.write_length(Some(value))
+    }
+
+    fn write_length(&mut self, value: Option<usize>) -> Result<usize> {
+        self.buffer.flush_bit();
+        if self.buffer.wpos < self.body_start || self.buffer.data.len() < self.body_start {
+            return Err(Error::SectionCursorMoved { body_start: self.body_start, wpos: self.buffer.wpos });
+        }
+        let length = value.unwrap_or_else(|| self.length());
+        let mut encoded = [0; 10];
+        let size = self.kind.encode(length, &mut encoded)?;
+
+        if self.kind.fixed_size().is_some() {
+            self.buffer.data.bytes_mut()[self.start..self.start + size].copy_from_slice(&encoded[..size]);
+        } else {
+            let len = self.buffer.data.len();
+            self.buffer.try_resize(len + size)?;
+            let bytes = self.buffer.data.bytes_mut();
+            bytes.copy_within(self.start..len, self.start + size);
+            bytes[self.start..self.start + size].copy_from_slice(&encoded[..size]);
+            self.buffer.wpos += size;
+            if self.buffer.rpos > self.start {
+                self.buffer.rpos += size;
+            }
+        }
+
+        Ok(length)
+    }
+}
+
+impl<'a, S: StorageMut> Deref for Section<'a, S> {
+    type Target = DataBuffer<S>;
+
+    fn deref(&self) -> &DataBuffer<S> {
+        self.buffer
+    }
+}
+
+impl<'a, S: StorageMut> DerefMut for Section<'a, S> {
+    fn deref_mut(&mut self) -> &mut DataBuffer<S> {
+        self.buffer
+    }
+}
+
+impl<'a, S: StorageMut> Drop for Section<'a, S> {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        if let Err(err) = self.write_length(None) {
+            if !panicking() {
+                panic!("{}", err);
+            }
+        }
+    }
+}
+
+/// Return true if the current thread is unwinding.
+#[cfg(feature = "std")]
+fn panicking() -> bool {
+    std::thread::panicking()
+}
+
+/// Without `std`, unwinding cannot be detected, and panicking while unwinding aborts,
+/// so a section failing on drop never panics: close it to handle the error.
+#[cfg(not(feature = "std"))]
+fn panicking() -> bool {
+    true
+}
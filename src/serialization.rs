@@ -0,0 +1,813 @@
+//! A serde `Serializer` and `Deserializer` backed by a [`DataBuffer`](../struct.DataBuffer.html),
+//! available with the `serde` feature.
+//!
+//! The format is not self-describing: values are written one after the other without any
+//! type or field information, in the same style as hand-written protocol encoders.
+//! Structs and tuples are written as their fields in order, `Option`s and `bool`s as a
+//! single byte, and enums as their variant index followed by their fields.
+//! How integers, strings and length prefixes are encoded is chosen with a [`Config`].
+
+use alloc::{borrow::ToOwned, string::ToString, vec::Vec};
+use core::{convert::TryInto, fmt::Display};
+
+use serde::{de, ser, Serialize};
+use serde::de::{DeserializeOwned, IntoDeserializer};
+
+use {DataBuffer, Error, LengthKind, Result, Section, Storage, StorageMut, Unmappable};
+
+/// The encoding of integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntEncoding {
+    /// Fixed size, big-endian, like `write_u16()`.
+    BigEndian,
+    /// Fixed size, little-endian, like `write_u16_le()`. Floats are little-endian too.
+    LittleEndian,
+    /// LEB128 variable-length integers, zigzag-encoded for signed values, like `write_var_u32()`.
+    VarInt,
+    /// `u16` values are written as smarts, like `write_smart()`, other integers are fixed size, big-endian.
+    Smart
+}
+
+/// The encoding of strings and chars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringEncoding {
+    /// UTF-8 prefixed with its length in bytes, like `write_str_prefixed()`.
+    Utf8(LengthKind),
+    /// Windows-1252 prefixed with its length, like `write_str_cp1252()`.
+    Cp1252(LengthKind),
+    /// Null-terminated Windows-1252, like `write_ntstr_cp1252()`.
+    Cp1252Terminated,
+    /// Java modified UTF-8, like `write_java_utf()`.
+    JavaUtf
+}
+
+/// The encoding choices of a [`Serializer`] or a [`Deserializer`].
+///
+/// The default configuration writes big-endian integers and UTF-8 strings, sequence,
+/// map and byte array lengths and enum variant indexes as `u32`, and accepts any length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    ints: IntEncoding,
+    strings: StringEncoding,
+    lengths: LengthKind,
+    max_len: usize
+}
+
+impl Config {
+    /// Construct the default configuration
+    pub fn new() -> Config {
+        Config {
+            ints: IntEncoding::BigEndian,
+            strings: StringEncoding::Utf8(LengthKind::U32),
+            lengths: LengthKind::U32,
+            max_len: usize::MAX
+        }
+    }
+
+    /// Set the encoding of integers
+    pub fn with_ints(mut self, ints: IntEncoding) -> Config {
+        self.ints = ints;
+        self
+    }
+
+    /// Set the encoding of strings and chars
+    pub fn with_strings(mut self, strings: StringEncoding) -> Config {
+        self.strings = strings;
+        self
+    }
+
+    /// Set the encoding of the length prefixes of sequences, maps and byte arrays,
+    /// which is also used for enum variant indexes
+    pub fn with_lengths(mut self, lengths: LengthKind) -> Config {
+        self.lengths = lengths;
+        self
+    }
+
+    /// Set the largest length accepted when deserializing a sequence, a map, a byte array
+    /// or a length-prefixed string, to protect against malicious length prefixes
+    pub fn with_max_len(mut self, max_len: usize) -> Config {
+        self.max_len = max_len;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl<S: StorageMut> DataBuffer<S> {
+    /// Serialize a value at the writing cursor, using the given configuration.
+    /// The program crash if the value cannot be serialized.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// #  use databuffer::serialization::*;
+    /// let mut buffer = DataBuffer::new();
+    /// let config = Config::new().with_ints(IntEncoding::VarInt).with_lengths(LengthKind::U8);
+    /// buffer.write_serde(&(300u32, vec![1u8, 2]), config);
+    /// assert_eq!(buffer.to_bytes(), vec![0xAC, 0x02, 0x02, 0x01, 0x02]);
+    /// assert_eq!(buffer.read_serde::<(u32, Vec<u8>)>(config), (300, vec![1, 2]));
+    /// ```
+    pub fn write_serde<T: Serialize + ?Sized>(&mut self, value: &T, config: Config) {
+        ::unwrap(self.try_write_serde(value, config))
+    }
+
+    /// Same as `write_serde()`, but return an error if the value cannot be serialized.
+    /// On error, the writing cursor is restored and the bytes appended to the buffer are dropped.
+    pub fn try_write_serde<T: Serialize + ?Sized>(&mut self, value: &T, config: Config) -> Result<()> {
+        self.write_or_rollback(|buf| value.serialize(&mut Serializer::new(buf, config)))
+    }
+}
+
+impl<S: Storage> DataBuffer<S> {
+    /// Deserialize a value at the reading cursor, using the given configuration.
+    /// The program crash if the value cannot be deserialized.
+    pub fn read_serde<T: DeserializeOwned>(&mut self, config: Config) -> T {
+        ::unwrap(self.try_read_serde(config))
+    }
+
+    /// Same as `read_serde()`, but return an error if the value cannot be deserialized.
+    /// The cursor is left untouched on error.
+    pub fn try_read_serde<T: DeserializeOwned>(&mut self, config: Config) -> Result<T> {
+        self.transaction(|buf| T::deserialize(&mut Deserializer::new(buf, config)))
+    }
+}
+
+/// A serde `Serializer` writing into a [`DataBuffer`](../struct.DataBuffer.html).
+pub struct Serializer<'a, S: StorageMut = Vec<u8>> {
+    buffer: &'a mut DataBuffer<S>,
+    config: Config
+}
+
+impl<'a, S: StorageMut> Serializer<'a, S> {
+    /// Construct a serializer writing at the writing cursor of the given buffer
+    pub fn new(buffer: &'a mut DataBuffer<S>, config: Config) -> Serializer<'a, S> {
+        Serializer { buffer, config }
+    }
+
+    fn write_length(&mut self, length: usize) -> Result<()> {
+        self.buffer.try_write_length(self.config.lengths, length)
+    }
+
+    fn write_variant(&mut self, index: u32) -> Result<()> {
+        self.write_length(index as usize)
+    }
+
+    /// Start a sequence or a map, prefixed with its number of elements. The number is
+    /// counted as the elements are written and written at the end if it is not known in advance.
+    fn begin_compound(&mut self, len: Option<usize>) -> Result<Compound<'_, S>> {
+        let config = self.config;
+        let target = match len {
+            Some(len) => {
+                self.write_length(len)?;
+                Target::Buffer(self.buffer)
+            }
+            None => Target::Section(self.buffer.try_begin_section(config.lengths)?)
+        };
+        Ok(Compound { target, config, count: 0 })
+    }
+
+    fn compound(&mut self) -> Compound<'_, S> {
+        Compound { target: Target::Buffer(self.buffer), config: self.config, count: 0 }
+    }
+}
+
+impl<'s, 'a, S: StorageMut> ser::Serializer for &'s mut Serializer<'a, S> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'s, S>;
+    type SerializeTuple = Compound<'s, S>;
+    type SerializeTupleStruct = Compound<'s, S>;
+    type SerializeTupleVariant = Compound<'s, S>;
+    type SerializeMap = Compound<'s, S>;
+    type SerializeStruct = Compound<'s, S>;
+    type SerializeStructVariant = Compound<'s, S>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.buffer.try_write_u8(v as u8)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.buffer.try_write_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        match self.config.ints {
+            IntEncoding::BigEndian | IntEncoding::Smart => self.buffer.try_write_i16(v),
+            IntEncoding::LittleEndian => self.buffer.try_write_i16_le(v),
+            IntEncoding::VarInt => self.buffer.try_write_var_i32(v as i32)
+        }
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        match self.config.ints {
+            IntEncoding::BigEndian | IntEncoding::Smart => self.buffer.try_write_i32(v),
+            IntEncoding::LittleEndian => self.buffer.try_write_i32_le(v),
+            IntEncoding::VarInt => self.buffer.try_write_var_i32(v)
+        }
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        match self.config.ints {
+            IntEncoding::BigEndian | IntEncoding::Smart => self.buffer.try_write_i64(v),
+            IntEncoding::LittleEndian => self.buffer.try_write_bytes(&v.to_le_bytes()),
+            IntEncoding::VarInt => self.buffer.try_write_var_i64(v)
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.buffer.try_write_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        match self.config.ints {
+            IntEncoding::BigEndian => self.buffer.try_write_u16(v),
+            IntEncoding::LittleEndian => self.buffer.try_write_u16_le(v),
+            IntEncoding::VarInt => self.buffer.try_write_var_u32(v as u32),
+            IntEncoding::Smart => {
+                if v > 0x7FFF {
+                    return Err(Error::ValueOutOfRange { value: v as u64, max: 0x7FFF });
+                }
+                self.buffer.try_write_smart(v)
+            }
+        }
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        match self.config.ints {
+            IntEncoding::BigEndian | IntEncoding::Smart => self.buffer.try_write_u32(v),
+            IntEncoding::LittleEndian => self.buffer.try_write_u32_le(v),
+            IntEncoding::VarInt => self.buffer.try_write_var_u32(v)
+        }
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        match self.config.ints {
+            IntEncoding::BigEndian | IntEncoding::Smart => self.buffer.try_write_u64(v),
+            IntEncoding::LittleEndian => self.buffer.try_write_bytes(&v.to_le_bytes()),
+            IntEncoding::VarInt => self.buffer.try_write_var_u64(v)
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        match self.config.ints {
+            IntEncoding::LittleEndian => self.buffer.try_write_bytes(&v.to_le_bytes()),
+            _ => self.buffer.try_write_f32(v)
+        }
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        match self.config.ints {
+            IntEncoding::LittleEndian => self.buffer.try_write_bytes(&v.to_le_bytes()),
+            _ => self.buffer.try_write_f64(v)
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        match self.config.strings {
+            StringEncoding::Utf8(kind) => self.buffer.try_write_str_prefixed(v, kind),
+            StringEncoding::Cp1252(kind) => self.buffer.try_write_str_cp1252(v, kind, Unmappable::Fail),
+            StringEncoding::Cp1252Terminated => self.buffer.try_write_ntstr_cp1252(v, Unmappable::Fail),
+            StringEncoding::JavaUtf => self.buffer.try_write_java_utf(v)
+        }
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.buffer.try_write_bytes_prefixed(v, self.config.lengths)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.buffer.try_write_u8(0)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        self.buffer.try_write_u8(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str) -> Result<()> {
+        self.write_variant(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, variant_index: u32,
+                                                        _variant: &'static str, value: &T) -> Result<()> {
+        self.write_variant(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Compound<'s, S>> {
+        self.begin_compound(len)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'s, S>> {
+        Ok(self.compound())
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'s, S>> {
+        Ok(self.compound())
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str,
+                               _len: usize) -> Result<Compound<'s, S>> {
+        self.write_variant(variant_index)?;
+        Ok(self.compound())
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Compound<'s, S>> {
+        self.begin_compound(len)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'s, S>> {
+        Ok(self.compound())
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str,
+                                _len: usize) -> Result<Compound<'s, S>> {
+        self.write_variant(variant_index)?;
+        Ok(self.compound())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+enum Target<'s, S: StorageMut> {
+    Buffer(&'s mut DataBuffer<S>),
+    Section(Section<'s, S>)
+}
+
+/// The serializer of the elements of sequences, tuples, maps and structs.
+pub struct Compound<'s, S: StorageMut = Vec<u8>> {
+    target: Target<'s, S>,
+    config: Config,
+    count: usize
+}
+
+impl<'s, S: StorageMut> Compound<'s, S> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let buffer = match self.target {
+            Target::Buffer(ref mut buffer) => &mut **buffer,
+            Target::Section(ref mut section) => &mut **section
+        };
+        value.serialize(&mut Serializer::new(buffer, self.config))
+    }
+
+    fn end(self) -> Result<()> {
+        match self.target {
+            Target::Buffer(_) => Ok(()),
+            Target::Section(section) => section.close_with(self.count).map(|_| ())
+        }
+    }
+}
+
+impl<'s, S: StorageMut> ser::SerializeSeq for Compound<'s, S> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.count += 1;
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, S: StorageMut> ser::SerializeTuple for Compound<'s, S> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, S: StorageMut> ser::SerializeTupleStruct for Compound<'s, S> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, S: StorageMut> ser::SerializeTupleVariant for Compound<'s, S> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, S: StorageMut> ser::SerializeMap for Compound<'s, S> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.count += 1;
+        self.element(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, S: StorageMut> ser::SerializeStruct for Compound<'s, S> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, S: StorageMut> ser::SerializeStructVariant for Compound<'s, S> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+/// A serde `Deserializer` reading from a [`DataBuffer`](../struct.DataBuffer.html).
+///
+/// **Note** : The format is not self-describing, so `deserialize_any` and
+/// `deserialize_ignored_any` are not supported.
+pub struct Deserializer<'a, S: Storage = Vec<u8>> {
+    buffer: &'a mut DataBuffer<S>,
+    config: Config
+}
+
+impl<'a, S: Storage> Deserializer<'a, S> {
+    /// Construct a deserializer reading at the reading cursor of the given buffer
+    pub fn new(buffer: &'a mut DataBuffer<S>, config: Config) -> Deserializer<'a, S> {
+        Deserializer { buffer, config }
+    }
+
+    fn read_length(&mut self) -> Result<usize> {
+        self.buffer.try_read_length_limited(self.config.lengths, self.config.max_len)
+    }
+
+    fn read_tag(&mut self, what: &str) -> Result<bool> {
+        let offset = self.buffer.remaining_start();
+        match self.buffer.try_read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            found => Err(Error::Message(format!("invalid {} byte 0x{:02x} at offset {}", what, found, offset)))
+        }
+    }
+
+    /// Read a string and pass it to the given function
+    fn read_str<T, F: FnOnce(&str) -> Result<T>>(&mut self, f: F) -> Result<T> {
+        let max_len = self.config.max_len;
+        match self.config.strings {
+            StringEncoding::Utf8(kind) => f(self.buffer.try_read_str_prefixed(kind, max_len)?),
+            StringEncoding::Cp1252(kind) => {
+                let length = self.buffer.try_peek_length(kind)?;
+                if length > max_len {
+                    return Err(Error::LengthLimitExceeded { length, max: max_len });
+                }
+                f(&self.buffer.try_read_str_cp1252(kind, Unmappable::Fail)?)
+            }
+            StringEncoding::Cp1252Terminated => f(&self.buffer.try_read_ntstr_cp1252(Unmappable::Fail)?),
+            StringEncoding::JavaUtf => f(&self.buffer.try_read_java_utf()?)
+        }
+    }
+
+    fn access(&mut self, len: usize) -> Access<'_, 'a, S> {
+        Access { de: self, remaining: len }
+    }
+}
+
+impl<'de, 's, 'a, S: Storage> de::Deserializer<'de> for &'s mut Deserializer<'a, S> {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Message("deserialize_any is not supported, the format is not self-describing".to_owned()))
+    }
+
+    fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(self.read_tag("bool")?)
+    }
+
+    fn deserialize_i8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8(self.buffer.try_read_i8()?)
+    }
+
+    fn deserialize_i16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = match self.config.ints {
+            IntEncoding::BigEndian | IntEncoding::Smart => self.buffer.try_read_i16()?,
+            IntEncoding::LittleEndian => self.buffer.try_read_i16_le()?,
+            IntEncoding::VarInt => {
+                let value = self.buffer.try_read_var_i32()?;
+                value.try_into().map_err(|_| Error::ValueOutOfRange { value: value as u64, max: i16::MAX as u64 })?
+            }
+        };
+        visitor.visit_i16(value)
+    }
+
+    fn deserialize_i32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = match self.config.ints {
+            IntEncoding::BigEndian | IntEncoding::Smart => self.buffer.try_read_i32()?,
+            IntEncoding::LittleEndian => self.buffer.try_read_i32_le()?,
+            IntEncoding::VarInt => self.buffer.try_read_var_i32()?
+        };
+        visitor.visit_i32(value)
+    }
+
+    fn deserialize_i64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = match self.config.ints {
+            IntEncoding::BigEndian | IntEncoding::Smart => self.buffer.try_read_i64()?,
+            IntEncoding::LittleEndian => i64::from_le_bytes(self.buffer.take_array()?),
+            IntEncoding::VarInt => self.buffer.try_read_var_i64()?
+        };
+        visitor.visit_i64(value)
+    }
+
+    fn deserialize_u8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(self.buffer.try_read_u8()?)
+    }
+
+    fn deserialize_u16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = match self.config.ints {
+            IntEncoding::BigEndian => self.buffer.try_read_u16()?,
+            IntEncoding::LittleEndian => self.buffer.try_read_u16_le()?,
+            IntEncoding::Smart => self.buffer.try_read_smart()?,
+            IntEncoding::VarInt => {
+                let value = self.buffer.try_read_var_u32()?;
+                value.try_into().map_err(|_| Error::ValueOutOfRange { value: value as u64, max: u16::MAX as u64 })?
+            }
+        };
+        visitor.visit_u16(value)
+    }
+
+    fn deserialize_u32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = match self.config.ints {
+            IntEncoding::BigEndian | IntEncoding::Smart => self.buffer.try_read_u32()?,
+            IntEncoding::LittleEndian => self.buffer.try_read_u32_le()?,
+            IntEncoding::VarInt => self.buffer.try_read_var_u32()?
+        };
+        visitor.visit_u32(value)
+    }
+
+    fn deserialize_u64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = match self.config.ints {
+            IntEncoding::BigEndian | IntEncoding::Smart => self.buffer.try_read_u64()?,
+            IntEncoding::LittleEndian => u64::from_le_bytes(self.buffer.take_array()?),
+            IntEncoding::VarInt => self.buffer.try_read_var_u64()?
+        };
+        visitor.visit_u64(value)
+    }
+
+    fn deserialize_f32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = match self.config.ints {
+            IntEncoding::LittleEndian => f32::from_le_bytes(self.buffer.take_array()?),
+            _ => self.buffer.try_read_f32()?
+        };
+        visitor.visit_f32(value)
+    }
+
+    fn deserialize_f64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = match self.config.ints {
+            IntEncoding::LittleEndian => f64::from_le_bytes(self.buffer.take_array()?),
+            _ => self.buffer.try_read_f64()?
+        };
+        visitor.visit_f64(value)
+    }
+
+    fn deserialize_char<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let ch = self.read_str(|string| {
+            let mut chars = string.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Ok(ch),
+                _ => Err(Error::Message(format!("expected a single character, found {:?}", string)))
+            }
+        })?;
+        visitor.visit_char(ch)
+    }
+
+    fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.read_str(|string| visitor.visit_str(string))
+    }
+
+    fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_length()?;
+        visitor.visit_bytes(self.buffer.try_read_slice(len)?)
+    }
+
+    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.read_tag("option")? {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: de::Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_length()?;
+        visitor.visit_seq(self.access(len))
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self.access(len))
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(self, _name: &'static str, len: usize,
+                                                     visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self.access(len))
+    }
+
+    fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_length()?;
+        visitor.visit_map(self.access(len))
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str],
+                                               visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self.access(fields.len()))
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str],
+                                             visitor: V) -> Result<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Message("identifiers are not supported, the format is not self-describing".to_owned()))
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Message("deserialize_ignored_any is not supported, the format is not self-describing".to_owned()))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// The access to the elements of sequences, tuples, maps and structs.
+struct Access<'s, 'a: 's, S: Storage> {
+    de: &'s mut Deserializer<'a, S>,
+    remaining: usize
+}
+
+impl<'de, 's, 'a, S: Storage> de::SeqAccess<'de> for Access<'s, 'a, S> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de, 's, 'a, S: Storage> de::MapAccess<'de> for Access<'s, 'a, S> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de, 's, 'a, S: Storage> de::EnumAccess<'de> for &'s mut Deserializer<'a, S> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let index = self.buffer.try_read_length(self.config.lengths)? as u32;
+        let value = seed.deserialize(index.into_deserializer())?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 's, 'a, S: Storage> de::VariantAccess<'de> for &'s mut Deserializer<'a, S> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self.access(len))
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self.access(fields.len()))
+    }
+}
+
+/// Serialize a value into a new buffer, using the given configuration.
+pub fn to_buffer<T: Serialize + ?Sized>(value: &T, config: Config) -> Result<DataBuffer> {
+    let mut buffer = DataBuffer::new();
+    buffer.try_write_serde(value, config)?;
+    Ok(buffer)
+}
+
+/// Deserialize a value from the start of the given bytes, using the given configuration.
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8], config: Config) -> Result<T> {
+    let mut buffer = DataBuffer::from_bytes(bytes);
+    T::deserialize(&mut Deserializer::new(&mut buffer, config))
+}
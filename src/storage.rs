@@ -0,0 +1,213 @@
+//! The memory a [`DataBuffer`](../struct.DataBuffer.html) reads from and writes into.
+//!
+//! Reading only needs a [`Storage`], which is any type dereferencing to the content of the
+//! buffer, while writing needs a [`StorageMut`]. Both are implemented for `Vec<u8>`,
+//! `Box<[u8]>`, `&mut [u8]`, `Cow<[u8]>` and [`Fixed`], and `Storage` alone for `&[u8]`.
+//! Other backing stores, such as shared memory, can be used by implementing these traits.
+//!
+//! #Example
+//!
+//! ```
+//! #  use databuffer::*;
+//! let bytes = [0x00, 0x01, 0x02];
+//! let mut buffer = DataBuffer::with_storage(&bytes[..]);
+//! assert_eq!(buffer.read_u16(), 1);
+//! assert_eq!(buffer.read_u8(), 2);
+//! ```
+
+use alloc::{borrow::Cow, boxed::Box, vec::Vec};
+use core::ops::Deref;
+
+use {Error, Result};
+
+/// The backing memory of a [`DataBuffer`](../struct.DataBuffer.html), sufficient for reading.
+///
+/// The content of the buffer is the slice the storage dereferences to.
+pub trait Storage: Deref<Target = [u8]> {
+    /// Return the number of bytes the storage can hold without reallocating
+    fn capacity(&self) -> usize {
+        self.len()
+    }
+}
+
+/// A [`Storage`] which can be written to.
+///
+/// Growing storages, such as `Vec<u8>`, are extended as values are written, while
+/// fixed-capacity storages, such as [`Fixed`] or `&mut [u8]`, report an error instead.
+pub trait StorageMut: Storage {
+    /// Return the content of the buffer, mutably
+    fn bytes_mut(&mut self) -> &mut [u8];
+
+    /// Change the length of the content, filling the new bytes with zeros.
+    /// An error is returned if the storage cannot hold `len` bytes, or cannot change its length.
+    fn try_resize(&mut self, len: usize) -> Result<()>;
+
+    /// Reserve room for at least `additional` more bytes, if the storage supports it
+    fn reserve(&mut self, additional: usize) {
+        let _ = additional;
+    }
+}
+
+impl Storage for Vec<u8> {
+    fn capacity(&self) -> usize {
+        Vec::capacity(self)
+    }
+}
+
+impl StorageMut for Vec<u8> {
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn try_resize(&mut self, len: usize) -> Result<()> {
+        self.resize(len, 0);
+        Ok(())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional)
+    }
+}
+
+/// Resize a storage whose length cannot change, which only succeeds if `len` is its length
+fn resize_in_place(bytes: &[u8], len: usize) -> Result<()> {
+    if len > bytes.len() {
+        return Err(Error::CapacityExceeded { needed: len, capacity: bytes.len() });
+    }
+    if len < bytes.len() {
+        return Err(Error::FixedLength { needed: len, len: bytes.len() });
+    }
+    Ok(())
+}
+
+impl Storage for Box<[u8]> {}
+
+/// A boxed slice has a fixed length: the whole slice is the content of the buffer,
+/// and writes past its end are an error, as is shrinking it with `clear()` or `compact()`.
+impl StorageMut for Box<[u8]> {
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn try_resize(&mut self, len: usize) -> Result<()> {
+        resize_in_place(self, len)
+    }
+}
+
+impl Storage for &[u8] {}
+
+impl Storage for &mut [u8] {}
+
+/// Like a boxed slice, the whole slice is the content of the buffer, and writes past
+/// its end or shrinking it are an error. See [`Fixed`] to write into a slice from an
+/// empty content instead.
+impl StorageMut for &mut [u8] {
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn try_resize(&mut self, len: usize) -> Result<()> {
+        resize_in_place(self, len)
+    }
+}
+
+impl<'a> Storage for Cow<'a, [u8]> {
+    fn capacity(&self) -> usize {
+        match *self {
+            Cow::Borrowed(bytes) => bytes.len(),
+            Cow::Owned(ref bytes) => bytes.capacity()
+        }
+    }
+}
+
+/// The borrowed bytes are copied on the first write.
+impl<'a> StorageMut for Cow<'a, [u8]> {
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        self.to_mut()
+    }
+
+    fn try_resize(&mut self, len: usize) -> Result<()> {
+        if len != self.len() {
+            self.to_mut().resize(len, 0);
+        }
+        Ok(())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.to_mut().reserve(additional)
+    }
+}
+
+/// A fixed-capacity storage over caller-provided memory, such as an inline `[u8; N]`
+/// or a `&mut [u8]` borrowed from a send buffer. It never allocates: the content grows
+/// as values are written, up to the size of the memory, and writing past it is an error.
+///
+/// #Example
+///
+/// ```
+/// #  use databuffer::*;
+/// let mut send_buffer = [0u8; 3];
+/// let mut buffer = DataBuffer::fixed(&mut send_buffer[..]);
+/// buffer.write_u16(0x1234);
+/// assert!(buffer.try_write_u16(0x5678).is_err());
+/// assert_eq!(buffer.len(), 2);
+/// assert_eq!(send_buffer, [0x12, 0x34, 0x00]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Fixed<B> {
+    bytes: B,
+    len: usize
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> Fixed<B> {
+    /// Wrap the given memory, with an empty content
+    pub fn new(bytes: B) -> Fixed<B> {
+        Fixed { bytes, len: 0 }
+    }
+
+    /// Wrap the given memory, with the first `len` bytes as the content.
+    /// The program crash if `len` is larger than the memory.
+    pub fn with_len(bytes: B, len: usize) -> Fixed<B> {
+        assert!(len <= bytes.as_ref().len(), "the content cannot be larger than the memory");
+        Fixed { bytes, len }
+    }
+
+    /// Return the wrapped memory
+    pub fn into_inner(self) -> B {
+        self.bytes
+    }
+}
+
+impl<B: AsRef<[u8]>> Deref for Fixed<B> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes.as_ref()[..self.len]
+    }
+}
+
+impl<B: AsRef<[u8]>> Storage for Fixed<B> {
+    fn capacity(&self) -> usize {
+        self.bytes.as_ref().len()
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> StorageMut for Fixed<B> {
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes.as_mut()[..self.len]
+    }
+
+    fn try_resize(&mut self, len: usize) -> Result<()> {
+        let capacity = self.capacity();
+        if len > capacity {
+            return Err(Error::CapacityExceeded { needed: len, capacity });
+        }
+        if len > self.len {
+            for byte in &mut self.bytes.as_mut()[self.len..len] {
+                *byte = 0;
+            }
+        }
+        self.len = len;
+        Ok(())
+    }
+}
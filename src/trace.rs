@@ -0,0 +1,363 @@
+use alloc::{borrow::ToOwned, boxed::Box, string::{String, ToString}, vec::Vec};
+use core::fmt::{self, Debug, Write};
+
+use {DataBuffer, Error, Result, Storage, StorageMut};
+
+/// One read or write operation recorded while tracing is enabled,
+/// see [`DataBuffer::enable_trace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    offset: usize,
+    len: usize,
+    bits: bool,
+    op: &'static str,
+    value: String,
+    label: Option<String>
+}
+
+impl TraceEntry {
+    /// Return the position of the first byte read or written.
+    /// For bit operations, this is the index of the first bit instead.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Return the number of bytes read or written.
+    /// For bit operations, this is the number of bits instead.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return true if the operation did not consume or produce anything, such as a failed read
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return true if the offset and the length are counted in bits
+    pub fn is_bits(&self) -> bool {
+        self.bits
+    }
+
+    /// Return the name of the operation, such as `read_u16_le_add`
+    pub fn op(&self) -> &str {
+        self.op
+    }
+
+    /// Return the value read or written, formatted with `Debug`.
+    /// Failed operations hold the error message instead.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Return the label given with [`DataBuffer::trace_label`], if any
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Format the offset, as `byte.bit` for bit operations
+    fn format_offset(&self) -> String {
+        if self.bits {
+            format!("{}.{}", self.offset / 8, self.offset % 8)
+        } else {
+            self.offset.to_string()
+        }
+    }
+
+    /// Format the length, suffixed with `b` for bit operations
+    fn format_len(&self) -> String {
+        if self.bits {
+            format!("{}b", self.len)
+        } else {
+            self.len.to_string()
+        }
+    }
+}
+
+/// The trace log of a buffer. Operations are only recorded at depth 0,
+/// so that an operation built on other operations is recorded once, under its own name.
+#[derive(Default)]
+pub(crate) struct Tracer {
+    entries: Vec<TraceEntry>,
+    depth: usize,
+    label: Option<String>
+}
+
+impl Tracer {
+    /// Record an operation, unless it is nested in another traced operation
+    pub(crate) fn record<F: FnOnce() -> String>(&mut self, op: &'static str, offset: usize, end: usize, bits: bool, value: F) {
+        if self.depth > 0 {
+            return;
+        }
+        self.entries.push(TraceEntry {
+            offset,
+            len: end.saturating_sub(offset),
+            bits,
+            op,
+            value: value(),
+            label: self.label.take()
+        });
+    }
+
+    /// Record a read operation from its result
+    pub(crate) fn record_result<T: Debug>(&mut self, op: &'static str, offset: usize, end: usize, result: &Result<T>) {
+        self.record(op, offset, end, false, || describe(result));
+    }
+
+    /// Record a failed read operation
+    pub(crate) fn record_error(&mut self, op: &'static str, offset: usize, err: &Error) {
+        self.record(op, offset, offset, false, || format!("error: {}", err));
+    }
+}
+
+fn describe<T: Debug>(result: &Result<T>) -> String {
+    match *result {
+        Ok(ref value) => format!("{:?}", value),
+        Err(ref err) => format!("error: {}", err)
+    }
+}
+
+fn describe_write<V: Debug + ?Sized>(result: &Result<()>, value: &V) -> String {
+    match *result {
+        Ok(()) => format!("{:?}", value),
+        Err(ref err) => format!("error: {}", err)
+    }
+}
+
+impl<S: Storage> DataBuffer<S> {
+    /// Start recording every read and write operation, with its offset, length, name and value.
+    /// Nothing is recorded until this is called, and tracing has no cost when disabled.
+    ///
+    /// Operations built on other operations, such as `read_string`, are recorded once under
+    /// their own name. Peeks and random access operations (`get_*_at`, `put_*_at`) are not recorded.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x01, 0x80, 0x00]);
+    /// buffer.enable_trace();
+    /// buffer.trace_label("opcode");
+    /// buffer.read_u8();
+    /// buffer.read_u16_le_add();
+    /// assert_eq!(buffer.trace()[1].op(), "read_u16_le_add");
+    /// assert_eq!(buffer.trace()[1].value(), "0");
+    /// ```
+    pub fn enable_trace(&mut self) {
+        if self.tracer.is_none() {
+            self.tracer = Some(Box::default());
+        }
+    }
+
+    /// Stop recording operations, and return the recorded log
+    pub fn disable_trace(&mut self) -> Vec<TraceEntry> {
+        self.tracer.take().map(|tracer| tracer.entries).unwrap_or_default()
+    }
+
+    /// Return true if operations are being recorded
+    pub fn is_tracing(&self) -> bool {
+        self.tracer.is_some()
+    }
+
+    /// Attach a label to the next recorded operation. Does nothing if tracing is disabled.
+    pub fn trace_label(&mut self, label: &str) {
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.label = Some(label.to_owned());
+        }
+    }
+
+    /// Return the operations recorded so far
+    pub fn trace(&self) -> &[TraceEntry] {
+        match self.tracer {
+            Some(ref tracer) => &tracer.entries,
+            None => &[]
+        }
+    }
+
+    /// Return the operations recorded so far and clear the log, tracing stays enabled
+    pub fn take_trace(&mut self) -> Vec<TraceEntry> {
+        match self.tracer.as_mut() {
+            Some(tracer) => core::mem::take(&mut tracer.entries),
+            None => vec![]
+        }
+    }
+
+    /// Format the recorded operations as a table, one operation per line.
+    /// Bit operations have their offset formatted as `byte.bit` and their length suffixed with `b`.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::new();
+    /// buffer.enable_trace();
+    /// buffer.trace_label("count");
+    /// buffer.write_u16(3);
+    /// buffer.write_bits(5, 3);
+    /// assert_eq!(buffer.trace_table(), "\
+    /// offset len op         value label
+    /// 0      2   write_u16  3     count
+    /// 2.0    3b  write_bits 5
+    /// ");
+    /// ```
+    pub fn trace_table(&self) -> String {
+        let header = ["offset", "len", "op", "value", "label"];
+        let rows: Vec<[String; 5]> = self.trace().iter().map(|entry| [
+            entry.format_offset(),
+            entry.format_len(),
+            entry.op.to_owned(),
+            entry.value.clone(),
+            entry.label.clone().unwrap_or_default()
+        ]).collect();
+
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let mut table = String::new();
+        let header = header.map(str::to_owned);
+        for row in core::iter::once(&header).chain(&rows) {
+            let mut line = String::new();
+            for (cell, width) in row.iter().zip(widths) {
+                let _ = write!(line, "{:width$} ", cell, width = width);
+            }
+            table.push_str(line.trim_end());
+            table.push('\n');
+        }
+        table
+    }
+
+    /// Format the recorded operations as a JSON array of objects, with the keys
+    /// `offset`, `len`, `bits`, `op`, `value` and `label`.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::from_bytes(&vec![0x00, 0x02]);
+    /// buffer.enable_trace();
+    /// buffer.read_u16();
+    /// assert_eq!(buffer.trace_json(),
+    ///     r#"[{"offset":0,"len":2,"bits":false,"op":"read_u16","value":"2","label":null}]"#);
+    /// ```
+    pub fn trace_json(&self) -> String {
+        let mut json = String::from("[");
+        for (i, entry) in self.trace().iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(json, "{{\"offset\":{},\"len\":{},\"bits\":{},\"op\":{},\"value\":{},\"label\":",
+                entry.offset, entry.len, entry.bits, JsonStr(entry.op), JsonStr(&entry.value));
+            match entry.label {
+                Some(ref label) => { let _ = write!(json, "{}}}", JsonStr(label)); }
+                None => json.push_str("null}")
+            }
+        }
+        json.push(']');
+        json
+    }
+
+    /// Return the offset a byte or bit read would start at
+    fn trace_read_start(&self, bits: bool) -> usize {
+        if bits { self.rpos * 8 + self.rbit } else { self.remaining_start() }
+    }
+
+    /// Return the offset a byte or bit write would start at
+    fn trace_write_start(&self, bits: bool) -> usize {
+        if bits {
+            self.wpos * 8 + self.wbit
+        } else if self.wbit > 0 {
+            self.wpos + 1
+        } else {
+            self.wpos
+        }
+    }
+
+    /// Run `f` with the trace depth increased, so the operations it runs are not recorded
+    pub(crate) fn untraced<T, F: FnOnce(&mut Self) -> T>(&mut self, f: F) -> T {
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.depth += 1;
+        }
+        let result = f(self);
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.depth -= 1;
+        }
+        result
+    }
+
+    fn traced<T, F, D>(&mut self, op: &'static str, bits: bool, read: bool, f: F, describe: D) -> T
+        where F: FnOnce(&mut Self) -> T, D: FnOnce(&T) -> String
+    {
+        if self.tracer.is_none() {
+            return f(self);
+        }
+
+        let start = if read { self.trace_read_start(bits) } else { self.trace_write_start(bits) };
+        let result = self.untraced(f);
+        let end = match (read, bits) {
+            (true, true) => self.rpos * 8 + self.rbit,
+            (true, false) => self.rpos,
+            (false, true) => self.wpos * 8 + self.wbit,
+            (false, false) => self.wpos
+        };
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.record(op, start, end, bits, || describe(&result));
+        }
+        result
+    }
+
+    /// Run a read operation, recording it under the given name
+    pub(crate) fn trace_read<T: Debug, F>(&mut self, op: &'static str, f: F) -> Result<T>
+        where F: FnOnce(&mut Self) -> Result<T>
+    {
+        self.traced(op, false, true, f, describe)
+    }
+
+    /// Run a bit read operation, recording it under the given name
+    pub(crate) fn trace_read_bits<T: Debug, F>(&mut self, op: &'static str, f: F) -> Result<T>
+        where F: FnOnce(&mut Self) -> Result<T>
+    {
+        self.traced(op, true, true, f, describe)
+    }
+}
+
+impl<S: StorageMut> DataBuffer<S> {
+    /// Run a write operation of the given value, recording it under the given name.
+    /// The write is rolled back if it fails.
+    pub(crate) fn trace_write<V, F>(&mut self, op: &'static str, value: &V, f: F) -> Result<()>
+        where V: Debug + ?Sized, F: FnOnce(&mut Self) -> Result<()>
+    {
+        self.traced(op, false, false, |buf| buf.write_or_rollback(f), |result| describe_write(result, value))
+    }
+
+    /// Run a bit write operation of the given value, recording it under the given name.
+    /// The write is rolled back if it fails.
+    pub(crate) fn trace_write_bits<V, F>(&mut self, op: &'static str, value: &V, f: F) -> Result<()>
+        where V: Debug + ?Sized, F: FnOnce(&mut Self) -> Result<()>
+    {
+        self.traced(op, true, false, |buf| buf.write_or_rollback(f), |result| describe_write(result, value))
+    }
+}
+
+/// Formats a string as a quoted JSON string
+struct JsonStr<'a>(&'a str);
+
+impl fmt::Display for JsonStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?
+            }
+        }
+        f.write_char('"')
+    }
+}
@@ -0,0 +1,356 @@
+//! The update blocks of RuneScape player and NPC updating.
+//!
+//! Every tick, the server sends the changes of each entity as a mask, telling which
+//! blocks follow, and the blocks themselves. Both the flag of each block in the mask and
+//! the order the blocks are written in depend on the revision, as do the transformations
+//! applied to their fields. A revision is described by implementing [`Revision`], and the
+//! blocks of an entity are accumulated in [`UpdateBlocks`] before being written with
+//! [`DataBuffer::write_update_blocks`](../struct.DataBuffer.html#method.write_update_blocks).
+//!
+//! #Example
+//!
+//! ```
+//! #  use databuffer::*;
+//! #  use databuffer::update::*;
+//! let mut blocks = UpdateBlocks::new();
+//! blocks.add(Block::Animation { id: 866, delay: 0 });
+//! blocks.add(Block::FaceEntity(32768 + 5));
+//!
+//! let mut buffer = DataBuffer::new();
+//! buffer.write_update_blocks(&blocks, &Rs317Player);
+//! assert_eq!(buffer.to_bytes(), vec![0x09, 0x62, 0x03, 0x00, 0x05, 0x80]);
+//! ```
+
+use alloc::{string::String, vec::Vec};
+
+use {cp1252, DataBuffer, Error, Result, StorageMut};
+
+/// The kind of an update block, see [`Block`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockKind {
+    Appearance,
+    Animation,
+    Graphics,
+    ForcedChat,
+    Chat,
+    FaceEntity,
+    FaceCoordinate,
+    Hit,
+    SecondaryHit,
+    ForcedMovement
+}
+
+impl BlockKind {
+    /// Return a description of the block, as reported in errors
+    fn description(&self) -> &'static str {
+        match *self {
+            BlockKind::Appearance => "the appearance block",
+            BlockKind::Animation => "the animation block",
+            BlockKind::Graphics => "the graphics block",
+            BlockKind::ForcedChat => "the forced chat block",
+            BlockKind::Chat => "the chat block",
+            BlockKind::FaceEntity => "the face entity block",
+            BlockKind::FaceCoordinate => "the face coordinate block",
+            BlockKind::Hit => "the hit block",
+            BlockKind::SecondaryHit => "the secondary hit block",
+            BlockKind::ForcedMovement => "the forced movement block"
+        }
+    }
+}
+
+/// An update block, holding the values sent to the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    /// The encoded appearance of a player: gender, equipment, colors, animations, name...
+    Appearance(Vec<u8>),
+    /// An animation, started after `delay` client cycles.
+    Animation { id: u16, delay: u8 },
+    /// A graphic (spot animation), drawn `height` units above the ground after `delay` client cycles.
+    Graphics { id: u16, height: u16, delay: u16 },
+    /// A text displayed above the entity, without being added to the chatbox.
+    ForcedChat(String),
+    /// A public chat message, `text` being the message as packed by the client.
+    Chat { color: u8, effects: u8, rights: u8, text: Vec<u8> },
+    /// The index of the entity to face, players being offset by 32768.
+    FaceEntity(u16),
+    /// The absolute coordinate to face.
+    FaceCoordinate { x: u16, y: u16 },
+    /// A hit splat, along with the health bar to display.
+    Hit { damage: u8, kind: u8, health: u8, max_health: u8 },
+    /// A second hit splat in the same tick.
+    SecondaryHit { damage: u8, kind: u8, health: u8, max_health: u8 },
+    /// A forced movement between two local positions, relative to the region base, from
+    /// the `start_tick` to the `end_tick` client cycles, while facing the given direction.
+    ForcedMovement { start_x: u8, start_y: u8, end_x: u8, end_y: u8, start_tick: u16, end_tick: u16, direction: u8 }
+}
+
+impl Block {
+    /// Return the kind of the block
+    pub fn kind(&self) -> BlockKind {
+        match *self {
+            Block::Appearance(_) => BlockKind::Appearance,
+            Block::Animation { .. } => BlockKind::Animation,
+            Block::Graphics { .. } => BlockKind::Graphics,
+            Block::ForcedChat(_) => BlockKind::ForcedChat,
+            Block::Chat { .. } => BlockKind::Chat,
+            Block::FaceEntity(_) => BlockKind::FaceEntity,
+            Block::FaceCoordinate { .. } => BlockKind::FaceCoordinate,
+            Block::Hit { .. } => BlockKind::Hit,
+            Block::SecondaryHit { .. } => BlockKind::SecondaryHit,
+            Block::ForcedMovement { .. } => BlockKind::ForcedMovement
+        }
+    }
+}
+
+/// The update protocol of a revision.
+pub trait Revision {
+    /// Return the blocks supported by the revision, in the order they are written,
+    /// along with their flag in the mask.
+    fn table(&self) -> &[(BlockKind, u32)];
+
+    /// Return the flag set in a mask byte when the mask continues in the next byte
+    fn extension_flag(&self) -> u8;
+
+    /// Write a block with the field transformations of the revision
+    fn write_block<S: StorageMut>(&self, buffer: &mut DataBuffer<S>, block: &Block) -> Result<()>;
+}
+
+/// The player updating of revision 317.
+///
+/// The mask is written as one byte, or two bytes, low byte first, with `0x40` set in the
+/// first byte.
+///
+/// There is no NPC counterpart: NPC updating has its own flags, order and field
+/// transformations, but also a transformation block changing the definition of the NPC,
+/// which has no [`BlockKind`]. It can be written by implementing [`Revision`] with an
+/// extension flag which is never needed, since the mask of NPCs fits in one byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rs317Player;
+
+const RS317_PLAYER_TABLE: [(BlockKind, u32); 10] = [
+    (BlockKind::ForcedMovement, 0x400),
+    (BlockKind::Graphics, 0x100),
+    (BlockKind::Animation, 0x8),
+    (BlockKind::ForcedChat, 0x4),
+    (BlockKind::Chat, 0x80),
+    (BlockKind::FaceEntity, 0x1),
+    (BlockKind::Appearance, 0x10),
+    (BlockKind::FaceCoordinate, 0x2),
+    (BlockKind::Hit, 0x20),
+    (BlockKind::SecondaryHit, 0x200)
+];
+
+/// Return `128 - val`, the byte transformation the client reverses by subtracting from 128.
+/// This is not the same as `write_u8_sub()`, which writes `val - 128`.
+fn sub_from_128(val: u8) -> u8 {
+    128u8.wrapping_sub(val)
+}
+
+/// Return `val * 2 + 1`, the coordinate of the center of the tile the client faces,
+/// or an error if it does not fit in 16 bits
+fn face_coordinate(val: u16) -> Result<u16> {
+    val.checked_mul(2).and_then(|val| val.checked_add(1))
+        .ok_or(Error::ValueOutOfRange { value: val as u64, max: 0x7FFF })
+}
+
+impl Revision for Rs317Player {
+    fn table(&self) -> &[(BlockKind, u32)] {
+        &RS317_PLAYER_TABLE
+    }
+
+    fn extension_flag(&self) -> u8 {
+        0x40
+    }
+
+    fn write_block<S: StorageMut>(&self, buffer: &mut DataBuffer<S>, block: &Block) -> Result<()> {
+        match *block {
+            Block::Appearance(ref bytes) => {
+                if bytes.len() > u8::MAX as usize {
+                    return Err(Error::ValueOutOfRange { value: bytes.len() as u64, max: u8::MAX as u64 });
+                }
+                buffer.try_write_u8_neg(bytes.len() as u8)?;
+                buffer.try_write_bytes(bytes)
+            }
+            Block::Animation { id, delay } => {
+                buffer.try_write_u16_le(id)?;
+                buffer.try_write_u8_neg(delay)
+            }
+            Block::Graphics { id, height, delay } => {
+                buffer.try_write_u16_le(id)?;
+                buffer.try_write_u32((height as u32) << 16 | delay as u32)
+            }
+            Block::ForcedChat(ref text) => {
+                for ch in text.chars() {
+                    match cp1252::encode(ch) {
+                        Some(byte) if byte != b'\n' => buffer.try_write_u8(byte)?,
+                        _ => return Err(Error::UnmappableChar { ch })
+                    }
+                }
+                buffer.try_write_u8(b'\n')
+            }
+            Block::Chat { color, effects, rights, ref text } => {
+                if text.len() > u8::MAX as usize {
+                    return Err(Error::ValueOutOfRange { value: text.len() as u64, max: u8::MAX as u64 });
+                }
+                buffer.try_write_u16_le((color as u16) << 8 | effects as u16)?;
+                buffer.try_write_u8(rights)?;
+                buffer.try_write_u8_neg(text.len() as u8)?;
+                for &byte in text.iter().rev() {
+                    buffer.try_write_u8(byte)?;
+                }
+                Ok(())
+            }
+            Block::FaceEntity(index) => buffer.try_write_u16_le(index),
+            Block::FaceCoordinate { x, y } => {
+                buffer.try_write_u16_le_add(face_coordinate(x)?)?;
+                buffer.try_write_u16_le(face_coordinate(y)?)
+            }
+            Block::Hit { damage, kind, health, max_health } => {
+                buffer.try_write_u8(damage)?;
+                buffer.try_write_u8_add(kind)?;
+                buffer.try_write_u8_neg(health)?;
+                buffer.try_write_u8(max_health)
+            }
+            Block::SecondaryHit { damage, kind, health, max_health } => {
+                buffer.try_write_u8(damage)?;
+                buffer.try_write_u8(sub_from_128(kind))?;
+                buffer.try_write_u8(health)?;
+                buffer.try_write_u8_neg(max_health)
+            }
+            Block::ForcedMovement { start_x, start_y, end_x, end_y, start_tick, end_tick, direction } => {
+                for &val in &[start_x, start_y, end_x, end_y] {
+                    buffer.try_write_u8(sub_from_128(val))?;
+                }
+                buffer.try_write_u16_le_add(start_tick)?;
+                buffer.try_write_u8((end_tick >> 8) as u8)?;
+                buffer.try_write_u8_add(end_tick as u8)?;
+                buffer.try_write_u8(sub_from_128(direction))
+            }
+        }
+    }
+}
+
+/// The update blocks of an entity for the current tick.
+///
+/// Adding a block replaces the block of the same kind, if any, and blocks are written in
+/// the order required by the revision, whatever order they were added in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateBlocks {
+    blocks: Vec<Block>
+}
+
+impl UpdateBlocks {
+    /// Construct an empty set of blocks
+    pub fn new() -> UpdateBlocks {
+        UpdateBlocks { blocks: Vec::new() }
+    }
+
+    /// Add a block, replacing the block of the same kind if any
+    pub fn add(&mut self, block: Block) {
+        let kind = block.kind();
+        match self.blocks.iter_mut().find(|b| b.kind() == kind) {
+            Some(existing) => *existing = block,
+            None => self.blocks.push(block)
+        }
+    }
+
+    /// Return the block of the given kind, if any
+    pub fn get(&self, kind: BlockKind) -> Option<&Block> {
+        self.blocks.iter().find(|b| b.kind() == kind)
+    }
+
+    /// Remove the block of the given kind, and return it
+    pub fn remove(&mut self, kind: BlockKind) -> Option<Block> {
+        let index = self.blocks.iter().position(|b| b.kind() == kind)?;
+        Some(self.blocks.remove(index))
+    }
+
+    /// Return true if the block of the given kind was added
+    pub fn contains(&self, kind: BlockKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    /// Return true if no block was added, in which case the entity needs no update
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Remove every block, to reuse the allocations on the next tick
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+
+    /// Return the mask of the added blocks, without the extension flags.
+    /// An error is returned if a block is not supported by the revision.
+    pub fn mask<R: Revision>(&self, revision: &R) -> Result<u32> {
+        let mut mask = 0;
+        for block in &self.blocks {
+            let kind = block.kind();
+            match revision.table().iter().find(|&&(k, _)| k == kind) {
+                Some(&(_, flag)) => mask |= flag,
+                None => return Err(Error::Unsupported { what: kind.description() })
+            }
+        }
+        Ok(mask)
+    }
+}
+
+impl<S: StorageMut> DataBuffer<S> {
+    /// Write the mask of the given blocks, followed by the blocks, as the given revision expects.
+    /// The program crash if a block is not supported by the revision, or cannot be encoded.
+    pub fn write_update_blocks<R: Revision>(&mut self, blocks: &UpdateBlocks, revision: &R) {
+        ::unwrap(self.try_write_update_blocks(blocks, revision))
+    }
+
+    /// Same as `write_update_blocks()`, but return an error if a block is not supported by
+    /// the revision, or cannot be encoded. Nothing is written on error.
+    pub fn try_write_update_blocks<R: Revision>(&mut self, blocks: &UpdateBlocks, revision: &R) -> Result<()> {
+        self.trace_write("write_update_blocks", blocks, |buf| {
+            let mask = blocks.mask(revision)?;
+            buf.try_write_update_mask(mask, revision.extension_flag())?;
+            for &(kind, _) in revision.table() {
+                if let Some(block) = blocks.get(kind) {
+                    revision.write_block(buf, block)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Write an update mask on as many bytes as needed, up to three, low byte first.
+    /// The extension flag is set in every byte followed by another one.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// #  use databuffer::*;
+    /// let mut buffer = DataBuffer::new();
+    /// buffer.write_update_mask(0x10100, 0x80); // buffer contains [0x80, 0x81, 0x01]
+    /// ```
+    pub fn write_update_mask(&mut self, mask: u32, extension_flag: u8) {
+        ::unwrap(self.try_write_update_mask(mask, extension_flag))
+    }
+
+    /// Same as `write_update_mask()`, but return an error if the mask does not fit in three bytes.
+    pub fn try_write_update_mask(&mut self, mask: u32, extension_flag: u8) -> Result<()> {
+        self.trace_write("write_update_mask", &mask, |buf| {
+            if mask > 0xFF_FFFF {
+                return Err(Error::ValueOutOfRange { value: mask as u64, max: 0xFF_FFFF });
+            }
+            let size = match mask {
+                0..=0xFF => 1,
+                0x100..=0xFFFF => 2,
+                _ => 3
+            };
+
+            let mut mask = mask;
+            for i in 0..size - 1 {
+                mask |= (extension_flag as u32) << (8 * i);
+            }
+            for i in 0..size {
+                buf.try_write_u8((mask >> (8 * i)) as u8)?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -1,7 +1,19 @@
+#![allow(clippy::bool_assert_comparison, clippy::useless_vec)]
+
 extern crate databuffer;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_derive;
 
 use databuffer::*;
-use std::io::{Read, Write};
+use databuffer::update::*;
+use databuffer::movement::{self, ListFormat, LocalEntity, Movement};
+use databuffer::palette::{Palette, PaletteChunk};
+use std::borrow::Cow;
+#[cfg(feature = "std")]
+use std::io::{BufRead, ErrorKind, IoSlice, Read, Seek, SeekFrom, Write};
 
 #[test]
 fn test_empty() {
@@ -160,6 +172,7 @@ fn test_flush_bit() {
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn test_read_empty_buffer() {
     let mut buffer = DataBuffer::new();
     buffer.write_u8(0xFF);
@@ -168,6 +181,7 @@ fn test_read_empty_buffer() {
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn test_read_exact_buffer() {
     let mut buffer = DataBuffer::new();
     buffer.write_u8(0xFF);
@@ -177,6 +191,7 @@ fn test_read_exact_buffer() {
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn test_read_larger_buffer() {
     let mut buffer = DataBuffer::new();
     buffer.write_u8(0xFF);
@@ -187,6 +202,7 @@ fn test_read_larger_buffer() {
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn test_read_larger_buffer_twice() {
     let mut buffer = DataBuffer::new();
     buffer.write_u8(0xFF);
@@ -199,6 +215,7 @@ fn test_read_larger_buffer_twice() {
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn test_write() {
     let mut buffer = DataBuffer::new();
     let _ = buffer.write(&[0x1, 0xFF, 0x45]);
@@ -206,11 +223,77 @@ fn test_write() {
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn test_flush() {
     let mut buffer = DataBuffer::new();
     let _ = buffer.flush();
 }
 
+#[test]
+#[cfg(feature = "std")]
+fn test_seek() {
+    let mut buffer = DataBuffer::from_bytes(&[1, 2, 3, 4]);
+    buffer.read_bit();
+    assert_eq!(buffer.stream_position().unwrap(), 1);
+    assert_eq!(buffer.seek(SeekFrom::Current(1)).unwrap(), 2);
+    assert_eq!(buffer.read_u8(), 3);
+    assert_eq!(buffer.seek(SeekFrom::End(-4)).unwrap(), 0);
+    assert_eq!(buffer.seek(SeekFrom::Start(10)).unwrap(), 4);
+    assert_eq!(buffer.seek(SeekFrom::Current(-5)).unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(buffer.get_rpos(), 4);
+
+    assert_eq!(buffer.seek_write(SeekFrom::Start(1)).unwrap(), 1);
+    buffer.write_u8(9);
+    assert_eq!(buffer.seek_write(SeekFrom::End(0)).unwrap(), 4);
+    buffer.write_u8(5);
+    assert_eq!(buffer.to_bytes(), vec![1, 9, 3, 4, 5]);
+    assert_eq!(buffer.get_rpos(), 4);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_buf_read() {
+    let mut buffer = DataBuffer::from_bytes(b"first\nsecond\nlast");
+    assert_eq!(buffer.read_u8(), b'f');
+    assert_eq!(buffer.fill_buf().unwrap(), b"irst\nsecond\nlast");
+    buffer.consume(4);
+    let lines: Vec<String> = buffer.lines().map(|line| line.unwrap()).collect();
+    assert_eq!(lines, vec!["", "second", "last"]);
+
+    let mut buffer = DataBuffer::from_bytes(&[1, 0, 2, 0]);
+    let mut chunk = Vec::new();
+    buffer.read_until(0, &mut chunk).unwrap();
+    assert_eq!(chunk, vec![1, 0]);
+    assert_eq!(buffer.read_u8(), 2);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_write_vectored() {
+    let slices = [IoSlice::new(&[1, 2]), IoSlice::new(&[]), IoSlice::new(&[3, 4, 5])];
+    let mut buffer = DataBuffer::new();
+    assert_eq!(buffer.write_vectored(&slices).unwrap(), 5);
+    assert_eq!(buffer.to_bytes(), vec![1, 2, 3, 4, 5]);
+
+    let mut fixed = DataBuffer::fixed([0u8; 4]);
+    assert_eq!(fixed.write_vectored(&slices).unwrap(), 4);
+    assert_eq!(fixed.to_bytes(), vec![1, 2, 3, 4]);
+    assert_eq!(fixed.write_vectored(&slices).unwrap(), 0);
+
+    // Like a slice, a fixed storage takes the bytes which fit, and `write_all()` fails after them
+    let mut fixed = DataBuffer::fixed([0u8; 3]);
+    fixed.write_bit(true);
+    assert_eq!(fixed.write(&[1, 2, 3]).unwrap(), 2);
+    assert_eq!(fixed.write(&[4]).unwrap(), 0);
+    let mut fixed = DataBuffer::fixed([0u8; 3]);
+    assert_eq!(fixed.write_all(&[1, 2, 3, 4]).unwrap_err().kind(), ErrorKind::WriteZero);
+    assert_eq!(fixed.to_bytes(), vec![1, 2, 3]);
+
+    let packets = [buffer, DataBuffer::from_bytes(&[6])];
+    let gathered: Vec<u8> = DataBuffer::io_slices(&packets).iter().flat_map(|slice| slice.to_vec()).collect();
+    assert_eq!(gathered, vec![1, 2, 3, 4, 5, 6]);
+}
+
 #[test]
 fn test_byte_packet_header() {
     let mut buffer = DataBuffer::create(3, PacketHeader::BYTE);
@@ -387,4 +470,1337 @@ fn test_deconstruct() {
     let vec = buffer.deconstruct();
 
     assert_eq!(format!("{:?}", vec), "[3, 2, 3, 4, 5, 6, 7]")
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_write_bits_length() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_bits(0b101, 3);
+    buffer.write_bits(0b11111, 5);
+    assert_eq!(buffer.to_bytes(), vec![0b10111111]);
+}
+
+#[test]
+fn test_bits_signed() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_bits_signed(-2, 5);
+    buffer.write_bits_signed(15, 5);
+    buffer.write_bits_signed(-16, 5);
+
+    assert_eq!(buffer.read_bits_signed(5), -2);
+    assert_eq!(buffer.read_bits_signed(5), 15);
+    assert_eq!(buffer.read_bits_signed(5), -16);
+}
+
+#[test]
+fn test_skip_bits() {
+    let mut buffer = DataBuffer::from_bytes(&[0b00010110, 0xFF]);
+    buffer.skip_bits(3);
+    assert_eq!(buffer.read_bits(3), 0b101);
+    buffer.skip_bits(2);
+    assert_eq!(buffer.read_u8(), 0xFF);
+}
+
+#[test]
+fn test_random_access_bits() {
+    let mut buffer = DataBuffer::from_bytes(&[0x00, 0xFF]);
+    buffer.set_bit(0, true);
+    buffer.set_bit(15, false);
+    assert!(buffer.get_bit(0));
+    assert!(!buffer.get_bit(15));
+    assert_eq!(buffer.to_bytes(), vec![0x80, 0xFE]);
+
+    buffer.set_bits_at(4, 0b10101, 5);
+    assert_eq!(buffer.get_bits_at(4, 5), 0b10101);
+    assert_eq!(buffer.get_rpos(), 0);
+    assert_eq!(buffer.get_wpos(), 2);
+}
+
+#[test]
+fn test_bit_errors() {
+    let mut buffer = DataBuffer::from_bytes(&[0xA5, 0x0F]);
+    let too_many = Error::ValueOutOfRange { value: 65, max: 64 };
+    assert_eq!(buffer.try_read_bits(65), Err(too_many.clone()));
+    assert_eq!(buffer.try_get_bits_at(0, 65), Err(too_many.clone()));
+    assert_eq!(buffer.try_set_bits_at(0, 0, 65), Err(too_many.clone()));
+    assert_eq!(buffer.try_get_bits_at(12, 5), Err(Error::OutOfBounds { offset: 1, size: 2, len: 2 }));
+    assert_eq!(buffer.try_set_bits_at(12, 0, 5), Err(Error::OutOfBounds { offset: 1, size: 2, len: 2 }));
+    assert_eq!(buffer.try_get_bits_at(12, 4), Ok(0xF));
+
+    buffer.read_bits(3);
+    assert_eq!(buffer.try_skip_bits(14), Err(Error::UnexpectedEof { needed: 3, remaining: 2 }));
+    assert_eq!(buffer.try_skip_bits(13), Ok(()));
+    assert!(buffer.try_read_bit().is_err());
+
+    let mut buffer = DataBuffer::new();
+    assert_eq!(buffer.try_write_bits(0, 65), Err(too_many.clone()));
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_bit_reservation() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_bit(true);
+    let count = buffer.reserve_bits(8);
+    buffer.write_bits(0b111, 3);
+    buffer.write_u8(0xAA);
+    buffer.fill_bits(count, 0xC3);
+
+    assert_eq!(count.bit_offset(), 1);
+    assert_eq!(buffer.to_bytes(), vec![0b11100001, 0b11110000, 0xAA]);
+    assert_eq!(buffer.get_wpos(), 3);
+}
+
+#[test]
+fn test_bit_reservation_overflow() {
+    let mut buffer = DataBuffer::new();
+    let count = buffer.reserve_bits(4);
+    assert_eq!(buffer.try_fill_bits(count, 16), Err(Error::ValueOutOfRange { value: 16, max: 15 }));
+    assert_eq!(buffer.try_fill_bits(count, 15), Ok(()));
+    assert_eq!(buffer.to_bytes(), vec![0xF0]);
+}
+
+#[test]
+fn test_mark_reset() {
+    let mut buffer = DataBuffer::from_bytes(&[0xFF, 0x00, 0x01]);
+    buffer.set_wpos(1);
+    buffer.write_bit(true);
+    buffer.read_bits(4);
+    let mark = buffer.mark();
+
+    assert_eq!(buffer.read_bits(4), 0xF);
+    buffer.write_bit(true);
+    buffer.write_u8(0x55);
+    buffer.reset(mark);
+
+    assert_eq!(buffer.read_bits(4), 0xF);
+    buffer.write_bit(false);
+    assert_eq!(buffer.to_bytes(), vec![0xFF, 0x80, 0x55]);
+}
+
+#[test]
+fn test_transaction() {
+    let mut buffer = DataBuffer::from_bytes(&[0x02, 0xAB]);
+
+    let result: Result<u16> = buffer.transaction(|buf| {
+        buf.read_u8();
+        Err(Error::ValueOutOfRange { value: 2, max: 1 })
+    });
+    assert!(result.is_err());
+    assert_eq!(buffer.get_rpos(), 0);
+
+    let result: Result<u8> = buffer.transaction(|buf| Ok(buf.read_u8()));
+    assert_eq!(result, Ok(0x02));
+    assert_eq!(buffer.get_rpos(), 1);
+}
+
+#[test]
+fn test_try_read_eof() {
+    let mut buffer = DataBuffer::from_bytes(&[0x01, 0x02, 0x03]);
+    assert_eq!(buffer.try_read_u32(), Err(Error::UnexpectedEof { needed: 4, remaining: 3 }));
+    assert_eq!(buffer.get_rpos(), 0);
+    assert_eq!(buffer.try_read_medium(), Ok(0x010203));
+    assert!(buffer.try_read_u8().is_err());
+    assert!(buffer.try_read_bit().is_err());
+}
+
+#[test]
+fn test_try_read_strings() {
+    let mut buffer = DataBuffer::from_bytes(&[0x41, 0x42]);
+    assert_eq!(buffer.try_read_ntstr(), Err(Error::UnexpectedEof { needed: 1, remaining: 0 }));
+    assert_eq!(buffer.get_rpos(), 0);
+    assert_eq!(buffer.try_read_dntstr(), Err(Error::UnexpectedByte { offset: 0, expected: 0, found: 0x41 }));
+
+    let mut buffer = DataBuffer::new();
+    buffer.write_u32(3);
+    buffer.write_bytes(&[0x41, 0xFF, 0x42]);
+    assert_eq!(buffer.try_read_string(), Err(Error::InvalidUtf8 { offset: 5 }));
+    assert_eq!(buffer.get_rpos(), 0);
+}
+
+#[test]
+fn test_peek() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_smart(300);
+    buffer.write_ntstr("hi");
+
+    assert_eq!(buffer.peek_u8(), 0x81);
+    assert_eq!(buffer.peek_u16(), 0x812C);
+    assert_eq!(buffer.peek_smart(), 300);
+    assert_eq!(buffer.peek_bytes(2), vec![0x81, 0x2C]);
+    assert_eq!(buffer.peek_bits(4), 0x8);
+    assert_eq!(buffer.get_rpos(), 0);
+
+    buffer.read_smart();
+    assert_eq!(buffer.peek_ntstr(), "hi");
+    assert_eq!(buffer.read_ntstr(), "hi");
+    assert!(buffer.try_peek_u8().is_err());
+    assert!(buffer.try_peek_smart().is_err());
+}
+
+#[test]
+fn test_peek_keeps_bit_position() {
+    let mut buffer = DataBuffer::from_bytes(&[0xF0, 0x0F]);
+    buffer.read_bits(3);
+    assert_eq!(buffer.peek_u8(), 0x0F);
+    assert_eq!(buffer.read_bits(5), 0x10);
+}
+
+#[test]
+fn test_peek_shared() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_u32_me(0x12345678);
+    buffer.write_var_u32(300);
+    let shared = &buffer;
+    assert_eq!(shared.peek_u32_me(), 0x12345678);
+    assert_eq!(shared.peek_u16(), buffer.get_u16_at(0));
+    assert_eq!(shared.try_peek_u64(), Err(Error::UnexpectedEof { needed: 8, remaining: 6 }));
+    assert_eq!(buffer.read_u32_me(), 0x12345678);
+    assert_eq!(buffer.peek_var_u32(), 300);
+    assert_eq!(buffer.remaining(), 2);
+}
+
+#[test]
+fn test_le_short_buffer() {
+    let mut buffer = DataBuffer::from_bytes(&[0x01, 0x02, 0x03]);
+    assert!(buffer.try_read_u32_le().is_err());
+    assert_eq!(buffer.read_u32_le(), 0x030201);
+    assert_eq!(buffer.read_u16_le(), 0);
+}
+
+#[test]
+fn test_smart_boundary() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_smart(127);
+    buffer.write_smart(128);
+
+    assert_eq!(buffer.read_smart(), 127);
+    assert_eq!(buffer.read_smart(), 128);
+}
+
+#[test]
+fn test_smart_out_of_range() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_smart(32767);
+    assert_eq!(buffer.try_write_smart(32768), Err(Error::ValueOutOfRange { value: 32768, max: 32767 }));
+    assert_eq!(buffer.len(), 2);
+    assert_eq!(buffer.read_smart(), 32767);
+}
+
+#[test]
+fn test_remaining() {
+    let mut buffer = DataBuffer::from_bytes(&[1, 2, 3, 4]);
+    assert_eq!(buffer.remaining(), 4);
+    buffer.read_bit();
+    assert_eq!(buffer.remaining(), 3);
+    assert!(buffer.has_remaining(3));
+    assert!(!buffer.has_remaining(4));
+    buffer.skip(2);
+    assert_eq!(buffer.read_remaining(), vec![4]);
+    assert_eq!(buffer.remaining(), 0);
+    assert!(buffer.try_skip(1).is_err());
+}
+
+#[test]
+fn test_reserve() {
+    let mut buffer = DataBuffer::new();
+    buffer.reserve(64);
+    assert!(buffer.capacity() >= 64);
+    assert_eq!(buffer.len(), 0);
+}
+
+#[test]
+fn test_compact() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_u32(0x01020304);
+    assert_eq!(buffer.read_u16(), 0x0102);
+    buffer.compact();
+
+    assert_eq!(buffer.len(), 2);
+    assert_eq!(buffer.get_rpos(), 0);
+    assert_eq!(buffer.get_wpos(), 2);
+
+    buffer.write_u8(5);
+    assert_eq!(buffer.read_remaining(), vec![3, 4, 5]);
+    buffer.compact();
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_section_fixed() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_u8(0xAA);
+    {
+        let mut section = buffer.begin_section(LengthKind::U8Neg);
+        section.write_u16(1);
+        section.write_u8(2);
+        assert_eq!(section.length(), 3);
+    }
+    {
+        let mut section = buffer.begin_section(LengthKind::U8Add);
+        section.write_u8(3);
+    }
+    buffer.write_u8(0xBB);
+
+    assert_eq!(buffer.to_bytes(), vec![0xAA, 0xFD, 0x00, 0x01, 0x02, 0x81, 0x03, 0xBB]);
+}
+
+#[test]
+fn test_section_variable() {
+    let mut buffer = DataBuffer::new();
+    {
+        let mut outer = buffer.begin_section(LengthKind::U32);
+        {
+            let mut smart = outer.begin_section(LengthKind::Smart);
+            smart.write_bytes(&[7; 200]);
+        }
+        let mut varint = outer.begin_section(LengthKind::VarInt);
+        varint.write_bytes(&[9; 130]);
+    }
+
+    assert_eq!(buffer.len(), 4 + 2 + 200 + 2 + 130);
+    assert_eq!(buffer.get_wpos(), buffer.len());
+    assert_eq!(buffer.read_u32(), 334);
+    assert_eq!(buffer.read_smart(), 200);
+    assert_eq!(buffer.read_bytes(200), vec![7; 200]);
+    assert_eq!(buffer.read_bytes(2), vec![0x82, 0x01]);
+    assert_eq!(buffer.read_remaining(), vec![9; 130]);
+}
+
+#[test]
+fn test_section_overflow() {
+    let mut buffer = DataBuffer::new();
+    let mut section = buffer.begin_section(LengthKind::U8);
+    section.write_bytes(&[0; 256]);
+    assert_eq!(section.close(), Err(Error::ValueOutOfRange { value: 256, max: 255 }));
+}
+
+#[test]
+#[cfg(feature = "std")]
+#[should_panic]
+fn test_section_overflow_on_drop() {
+    let mut buffer = DataBuffer::new();
+    let mut section = buffer.begin_section(LengthKind::Smart);
+    section.write_bytes(&[0; 32768]);
+}
+
+#[test]
+fn test_section_cursor_moved() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_u8(1);
+    let mut section = buffer.begin_section(LengthKind::U16);
+    section.write_bytes(&[2; 4]);
+    section.set_wpos(1);
+    assert_eq!(section.close(), Err(Error::SectionCursorMoved { body_start: 3, wpos: 1 }));
+
+    let mut buffer = DataBuffer::new();
+    let mut section = buffer.begin_section(LengthKind::Smart);
+    section.write_bytes(&[2; 4]);
+    section.clear();
+    section.write_u8(3);
+    assert_eq!(section.close(), Ok(1));
+}
+
+#[test]
+fn test_section_splice_keeps_rpos() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_u8(1);
+    let mut section = buffer.begin_section(LengthKind::Smart);
+    section.write_bytes(&[2, 3]);
+    section.read_u8();
+    assert_eq!(section.read_u8(), 2);
+    assert_eq!(section.close(), Ok(2));
+    assert_eq!(buffer.read_u8(), 3);
+    assert_eq!(buffer.to_bytes(), vec![1, 2, 2, 3]);
+}
+
+#[test]
+fn test_put_get_at() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_bytes(&[0; 12]);
+    buffer.read_u8();
+
+    buffer.put_u16_at(0, 0xBEEF);
+    buffer.put_u16_le_add_at(2, 0x1234);
+    buffer.put_u8_neg_at(4, 5);
+    buffer.put_u32_me_at(5, 0xA1B2C3D4);
+    buffer.put_medium_at(9, 0x010203);
+
+    assert_eq!(buffer.get_rpos(), 1);
+    assert_eq!(buffer.get_wpos(), 12);
+    assert_eq!(buffer.get_u16_at(0), 0xBEEF);
+    assert_eq!(buffer.get_u16_le_add_at(2), 0x1234);
+    assert_eq!(buffer.get_u8_neg_at(4), 5);
+    assert_eq!(buffer.get_u32_me_at(5), 0xA1B2C3D4);
+    assert_eq!(buffer.get_medium_at(9), 0x010203);
+    assert_eq!(buffer.get_bytes_at(0, 2), vec![0xBE, 0xEF]);
+
+    buffer.set_rpos(2);
+    assert_eq!(buffer.read_u16_le_add(), 0x1234);
+}
+
+#[test]
+fn test_put_at_out_of_bounds() {
+    let mut buffer = DataBuffer::from_bytes(&[0; 3]);
+    assert_eq!(buffer.try_put_u32_at(0, 1), Err(Error::OutOfBounds { offset: 0, size: 4, len: 3 }));
+    assert!(buffer.try_get_u16_at(2).is_err());
+    assert!(buffer.try_put_bytes_at(usize::MAX, &[1]).is_err());
+    assert_eq!(buffer.to_bytes(), vec![0; 3]);
+}
+
+#[test]
+fn test_large_short_packet_header() {
+    let mut buffer = DataBuffer::create(3, PacketHeader::SHORT);
+    buffer.write_bytes(&[1; 300]);
+    buffer.finish();
+    buffer.write_u8(2);
+
+    assert_eq!(buffer.get_u16_at(1), 300);
+    assert_eq!(buffer.len(), 304);
+}
+
+#[test]
+fn test_var_int() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_var_u32(0);
+    buffer.write_var_u32(127);
+    buffer.write_var_u32(128);
+    buffer.write_var_u32(u32::MAX);
+    buffer.write_var_u64(u64::MAX);
+    buffer.write_var_i32(-1);
+    buffer.write_var_i32(i32::MIN);
+    buffer.write_var_i64(i64::MAX);
+    buffer.write_var_i64(-64);
+
+    assert_eq!(buffer.get_bytes_at(0, 4), vec![0x00, 0x7F, 0x80, 0x01]);
+    assert_eq!(buffer.read_var_u32(), 0);
+    assert_eq!(buffer.read_var_u32(), 127);
+    assert_eq!(buffer.read_var_u32(), 128);
+    assert_eq!(buffer.read_var_u32(), u32::MAX);
+    assert_eq!(buffer.read_var_u64(), u64::MAX);
+    assert_eq!(buffer.read_var_i32(), -1);
+    assert_eq!(buffer.read_var_i32(), i32::MIN);
+    assert_eq!(buffer.read_var_i64(), i64::MAX);
+    assert_eq!(buffer.peek_var_i64(), -64);
+    assert_eq!(buffer.read_bytes(1), vec![0x7F]);
+}
+
+#[test]
+fn test_var_int_malformed() {
+    let mut buffer = DataBuffer::from_bytes(&[0xFF, 0xFF, 0xFF, 0xFF, 0x10]);
+    assert_eq!(buffer.try_read_var_u32(), Err(Error::VarIntOverflow { offset: 0 }));
+    assert_eq!(buffer.get_rpos(), 0);
+    assert_eq!(buffer.try_read_var_u64(), Ok(0x1_0FFF_FFFF));
+
+    let mut buffer = DataBuffer::from_bytes(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x01]);
+    assert_eq!(buffer.try_read_var_u32(), Err(Error::VarIntOverflow { offset: 0 }));
+
+    let mut buffer = DataBuffer::from_bytes(&[0x81, 0x80, 0x00]);
+    assert_eq!(buffer.try_read_var_u32(), Err(Error::OverlongVarInt { offset: 0 }));
+
+    let mut buffer = DataBuffer::from_bytes(&[0x81]);
+    assert_eq!(buffer.try_read_var_u32(), Err(Error::UnexpectedEof { needed: 1, remaining: 0 }));
+    assert_eq!(buffer.get_rpos(), 0);
+}
+
+#[test]
+fn test_var_prefixed() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_var_bytes(&[1, 2, 3]);
+    buffer.write_var_str("héllo");
+
+    assert_eq!(buffer.read_var_bytes(), vec![1, 2, 3]);
+    assert_eq!(buffer.read_var_str(), "héllo");
+
+    let mut buffer = DataBuffer::from_bytes(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x00]);
+    assert_eq!(buffer.try_read_var_bytes(), Err(Error::UnexpectedEof { needed: 0xFFFFFFFF, remaining: 1 }));
+}
+
+#[test]
+fn test_cp1252_table() {
+    for byte in 0..=255u8 {
+        if let Some(ch) = cp1252::decode(byte) {
+            assert_eq!(cp1252::encode(ch), Some(byte));
+        }
+    }
+    assert_eq!(cp1252::decode(0x9F), Some('Ÿ'));
+    assert_eq!(cp1252::decode(0xE9), Some('é'));
+    assert_eq!(cp1252::encode('Ā'), None);
+}
+
+#[test]
+fn test_cp1252_strings() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_ntstr_cp1252("€é", Unmappable::Fail);
+    buffer.write_dntstr_cp1252("“x”", Unmappable::Fail);
+    buffer.write_str_cp1252("™", LengthKind::Smart, Unmappable::Fail);
+
+    assert_eq!(buffer.get_bytes_at(0, 3), vec![0x80, 0xE9, 0x00]);
+    assert_eq!(buffer.read_ntstr_cp1252(Unmappable::Fail), "€é");
+    assert_eq!(buffer.read_dntstr_cp1252(Unmappable::Fail), "“x”");
+    assert_eq!(buffer.peek_str_cp1252(LengthKind::Smart, Unmappable::Fail), "™");
+    assert_eq!(buffer.read_bytes(2), vec![0x01, 0x99]);
+}
+
+#[test]
+fn test_cp1252_unmappable() {
+    let mut buffer = DataBuffer::new();
+    assert_eq!(buffer.try_write_ntstr_cp1252("a😀", Unmappable::Fail), Err(Error::UnmappableChar { ch: '😀' }));
+    assert_eq!(buffer.try_write_ntstr_cp1252("a\0b", Unmappable::Fail), Err(Error::UnmappableChar { ch: '\0' }));
+    assert!(buffer.is_empty());
+
+    buffer.write_ntstr_cp1252("a😀", Unmappable::Replace);
+    assert_eq!(buffer.to_bytes(), vec![0x61, 0x3F, 0x00]);
+
+    let mut buffer = DataBuffer::from_bytes(&[0x61, 0x81, 0x00]);
+    assert_eq!(buffer.try_read_ntstr_cp1252(Unmappable::Fail), Err(Error::UnmappableByte { offset: 1, byte: 0x81 }));
+    assert_eq!(buffer.get_rpos(), 0);
+    assert_eq!(buffer.read_ntstr_cp1252(Unmappable::Replace), "a\u{FFFD}");
+}
+
+#[test]
+fn test_java_utf() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_java_utf("héllo\0😀");
+
+    assert_eq!(buffer.to_bytes(), vec![
+        0x00, 0x0E, 0x68, 0xC3, 0xA9, 0x6C, 0x6C, 0x6F, 0xC0, 0x80,
+        0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80
+    ]);
+    assert_eq!(buffer.read_java_utf(), "héllo\0😀");
+}
+
+#[test]
+fn test_java_utf_malformed() {
+    let mut buffer = DataBuffer::from_bytes(&[0x00, 0x02, 0x61, 0xC3]);
+    assert_eq!(buffer.try_read_java_utf(), Err(Error::InvalidModifiedUtf8 { offset: 3 }));
+    assert_eq!(buffer.get_rpos(), 0);
+
+    let mut buffer = DataBuffer::from_bytes(&[0x00, 0x03, 0xED, 0xA0, 0xBD]);
+    assert!(buffer.try_read_java_utf().is_err());
+
+    let mut buffer = DataBuffer::new();
+    let long = "\u{800}".repeat(21846);
+    assert!(buffer.try_write_java_utf(&long).is_err());
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_java_data_io() {
+    use databuffer::java::{DataInput, DataOutput};
+
+    let mut buffer = DataBuffer::new();
+    buffer.write_boolean(true).unwrap();
+    buffer.write_byte(-2).unwrap();
+    buffer.write_short(-300).unwrap();
+    buffer.write_char(0xD83D).unwrap();
+    buffer.write_int(-5).unwrap();
+    buffer.write_long(1 << 40).unwrap();
+    buffer.write_float(1.5).unwrap();
+    buffer.write_double(-0.25).unwrap();
+    buffer.write_chars("hi").unwrap();
+    buffer.write_utf("ok").unwrap();
+
+    assert!(buffer.read_boolean().unwrap());
+    assert_eq!(buffer.read_unsigned_byte().unwrap(), 0xFE);
+    assert_eq!(buffer.read_unsigned_short().unwrap(), 0xFED4);
+    assert_eq!(buffer.read_char().unwrap(), 0xD83D);
+    assert_eq!(buffer.read_int().unwrap(), -5);
+    assert_eq!(buffer.read_long().unwrap(), 1 << 40);
+    assert_eq!(buffer.read_float().unwrap(), 1.5);
+    assert_eq!(buffer.read_double().unwrap(), -0.25);
+    let mut chars = [0; 4];
+    buffer.read_fully(&mut chars).unwrap();
+    assert_eq!(chars, [0x00, 0x68, 0x00, 0x69]);
+    assert_eq!(buffer.read_utf().unwrap(), "ok");
+    assert!(buffer.read_byte().is_err());
+    assert_eq!(buffer.skip_bytes(10), 0);
+}
+
+#[test]
+fn test_prefixed() {
+    let kinds = [LengthKind::U8, LengthKind::U8Neg, LengthKind::U8Add, LengthKind::U16,
+                 LengthKind::U32, LengthKind::Smart, LengthKind::VarInt];
+    for &kind in kinds.iter() {
+        let mut buffer = DataBuffer::new();
+        buffer.write_str_prefixed("hello", kind);
+        buffer.write_bytes_prefixed(&[1; 200], kind);
+
+        assert_eq!(buffer.read_string_prefixed(kind, 5), "hello");
+        assert_eq!(buffer.read_bytes_prefixed(kind, 200), vec![1; 200]);
+    }
+}
+
+#[test]
+fn test_prefixed_limits() {
+    let mut buffer = DataBuffer::new();
+    assert_eq!(buffer.try_write_bytes_prefixed(&[0; 256], LengthKind::U8), Err(Error::ValueOutOfRange { value: 256, max: 255 }));
+    assert!(buffer.is_empty());
+
+    buffer.write_str_prefixed("hello", LengthKind::Smart);
+    assert_eq!(buffer.try_read_string_prefixed(LengthKind::Smart, 4), Err(Error::LengthLimitExceeded { length: 5, max: 4 }));
+    assert_eq!(buffer.get_rpos(), 0);
+
+    let mut buffer = DataBuffer::from_bytes(&[0xFF, 0xFF, 0xFF, 0xFF, 0x61]);
+    assert_eq!(buffer.try_read_string_prefixed(LengthKind::U32, usize::MAX), Err(Error::UnexpectedEof { needed: 0xFFFFFFFF, remaining: 1 }));
+    assert_eq!(buffer.try_read_string(), Err(Error::UnexpectedEof { needed: 0xFFFFFFFF, remaining: 1 }));
+}
+
+#[test]
+fn test_string_lossy() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_u32(4);
+    buffer.write_bytes(&[0x61, 0xC3, 0x28, 0x62]);
+    buffer.write_str_prefixed("ok", LengthKind::U8);
+
+    assert_eq!(buffer.try_read_string(), Err(Error::InvalidUtf8 { offset: 5 }));
+    assert_eq!(buffer.get_rpos(), 0);
+    assert_eq!(buffer.read_string_lossy(), "a\u{FFFD}(b");
+    assert_eq!(buffer.read_string_prefixed_lossy(LengthKind::U8, 2), "ok");
+}
+
+#[test]
+fn test_borrowed_reads() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_str("hello");
+    buffer.write_str_prefixed("world", LengthKind::Smart);
+    buffer.write_u8(0xFF);
+
+    assert_eq!(buffer.read_str(), "hello");
+    assert_eq!(buffer.read_str_prefixed(LengthKind::Smart, 5), "world");
+    assert_eq!(buffer.read_slice(1), &[0xFF]);
+    assert!(buffer.try_read_slice(1).is_err());
+
+    let mut buffer = DataBuffer::from_bytes(&[0x02, 0x61, 0xFF]);
+    assert_eq!(buffer.try_read_str_prefixed(LengthKind::U8, 2), Err(Error::InvalidUtf8 { offset: 2 }));
+    assert_eq!(buffer.try_read_str_prefixed(LengthKind::U8, 1), Err(Error::LengthLimitExceeded { length: 2, max: 1 }));
+    assert_eq!(buffer.get_rpos(), 0);
+}
+
+#[test]
+fn test_display() {
+    let buffer = DataBuffer::from_bytes(&[0x00, 0xAB]);
+    assert_eq!(format!("{}", buffer), "0x00 0xab");
+    assert_eq!(format!("{}", DataBuffer::new()), "");
+}
+
+#[test]
+fn test_hexdump() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_str("hello");
+    buffer.read_u32();
+
+    assert_eq!(buffer.hexdump(),
+        "00000000: 00 00 00 05 68 65 6c 6c 6f                       ....hello\n\
+         \x20                     R              W\n");
+
+    let mut buffer = DataBuffer::from_bytes(&[0x41; 17]);
+    buffer.set_wpos(0);
+    assert_eq!(buffer.hexdump(),
+        "00000000: 41 41 41 41 41 41 41 41 41 41 41 41 41 41 41 41  AAAAAAAAAAAAAAAA\n\
+         \x20         *\n\
+         00000010: 41                                               A\n");
+}
+
+#[test]
+fn test_array_exports() {
+    let buffer = DataBuffer::from_bytes(&[0x80; 17]);
+    let rust = buffer.to_rust_array("DATA");
+    assert!(rust.starts_with("const DATA: [u8; 17] = [\n    0x80, 0x80,"));
+    assert!(rust.ends_with("0x80,\n    0x80,\n];\n"));
+
+    let buffer = DataBuffer::from_bytes(&[0x7F, 0x80]);
+    assert_eq!(buffer.to_c_array("data"), "const unsigned char data[2] = {0x7f, 0x80};\n");
+    assert_eq!(buffer.to_java_array("data"), "byte[] data = {0x7f, (byte) 0x80};\n");
+}
+
+#[test]
+fn test_trace_records_outermost_operations() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_str("hi");
+    buffer.write_u16_le_add(0x1234);
+    buffer.write_bits(0b101, 3);
+
+    let mut buffer = DataBuffer::from_bytes(&buffer.to_bytes());
+    assert!(!buffer.is_tracing());
+    buffer.enable_trace();
+    buffer.trace_label("name");
+    assert_eq!(buffer.read_string(), "hi");
+    assert_eq!(buffer.peek_u8(), 0xB4);
+    assert_eq!(buffer.read_u16_le_add(), 0x1234);
+    assert_eq!(buffer.read_bits(3), 0b101);
+
+    let trace = buffer.trace();
+    assert_eq!(trace.len(), 3);
+    assert_eq!((trace[0].offset(), trace[0].len(), trace[0].op()), (0, 6, "read_string"));
+    assert_eq!(trace[0].value(), "\"hi\"");
+    assert_eq!(trace[0].label(), Some("name"));
+    assert_eq!((trace[1].offset(), trace[1].len(), trace[1].op()), (6, 2, "read_u16_le_add"));
+    assert_eq!(trace[1].value(), "4660");
+    assert_eq!(trace[1].label(), None);
+    assert!(trace[2].is_bits());
+    assert_eq!((trace[2].offset(), trace[2].len(), trace[2].op()), (64, 3, "read_bits"));
+
+    assert_eq!(buffer.take_trace().len(), 3);
+    assert!(buffer.trace().is_empty());
+    assert!(buffer.is_tracing());
+}
+
+#[test]
+fn test_trace_errors_and_borrowed_reads() {
+    let mut buffer = DataBuffer::from_bytes(&[0x2, 0x68, 0x69, 0x5]);
+    buffer.enable_trace();
+    assert_eq!(buffer.read_str_prefixed(LengthKind::U8, 16), "hi");
+    assert_eq!(buffer.read_slice(1), &[0x5]);
+    assert!(buffer.try_read_u16().is_err());
+
+    let trace = buffer.disable_trace();
+    assert_eq!((trace[0].offset(), trace[0].len(), trace[0].op()), (0, 3, "read_str_prefixed"));
+    assert_eq!((trace[1].offset(), trace[1].len(), trace[1].op()), (3, 1, "read_slice"));
+    assert_eq!(trace[1].value(), "[5]");
+    assert_eq!(trace[2].op(), "read_u16");
+    assert!(trace[2].is_empty());
+    assert!(trace[2].value().starts_with("error: "));
+    assert!(!buffer.is_tracing());
+    assert!(buffer.trace().is_empty());
+}
+
+#[test]
+fn test_trace_json() {
+    let mut buffer = DataBuffer::new();
+    buffer.enable_trace();
+    buffer.trace_label("say \"hi\"");
+    buffer.write_ntstr("a\\b");
+    assert_eq!(buffer.trace_json(),
+        r#"[{"offset":0,"len":4,"bits":false,"op":"write_ntstr","value":"\"a\\\\b\"","label":"say \"hi\""}]"#);
+    assert_eq!(DataBuffer::new().trace_json(), "[]");
+}
+
+#[test]
+fn test_trace_write_length() {
+    let mut buffer = DataBuffer::new();
+    buffer.enable_trace();
+    buffer.write_length(LengthKind::Smart, 300);
+    let trace = buffer.trace();
+    assert_eq!((trace[0].offset(), trace[0].len(), trace[0].op()), (0, 2, "write_length"));
+    assert_eq!(trace[0].value(), "300");
+}
+
+#[test]
+fn test_fixed_capacity() {
+    let mut buffer = DataBuffer::fixed([0u8; 5]);
+    buffer.write_u16(0x1234);
+    assert_eq!(buffer.try_write_u32(1), Err(Error::CapacityExceeded { needed: 6, capacity: 5 }));
+    assert_eq!(buffer.len(), 2);
+    assert_eq!(buffer.get_wpos(), 2);
+
+    // A composite write is dropped entirely when it does not fit
+    assert!(buffer.try_write_ntstr_cp1252("abc", Unmappable::Fail).is_err());
+    assert_eq!(buffer.to_bytes(), vec![0x12, 0x34]);
+
+    buffer.write_bits(5, 3);
+    assert!(buffer.try_write_bits(0, 30).is_err());
+    assert_eq!(buffer.to_bytes(), vec![0x12, 0x34, 0xA0]);
+    buffer.write_bits(1, 13);
+    assert_eq!(buffer.to_bytes(), vec![0x12, 0x34, 0xA0, 0x01]);
+    assert_eq!(buffer.read_u16(), 0x1234);
+
+    buffer.clear();
+    buffer.write_u8(7);
+    assert_eq!(buffer.storage().capacity(), 5);
+    assert_eq!(buffer.deconstruct().into_inner()[..4], [7, 0x34, 0xA0, 0x01]);
+}
+
+#[test]
+fn test_rollback_restores_overwritten_bytes() {
+    let mut buffer = DataBuffer::fixed([0u8; 4]);
+    buffer.write_bytes(&[1, 2, 3, 4]);
+    buffer.set_wpos(0);
+    assert!(buffer.try_write_bytes_prefixed(&[9; 4], LengthKind::U8).is_err());
+    assert_eq!(buffer.to_bytes(), vec![1, 2, 3, 4]);
+    assert_eq!(buffer.get_wpos(), 0);
+
+    buffer.set_wpos(1);
+    buffer.write_bits(0, 4);
+    assert!(buffer.try_write_ntstr_cp1252("abc", Unmappable::Fail).is_err());
+    assert_eq!(buffer.to_bytes(), vec![1, 2, 3, 4]);
+    buffer.write_bits(0xF, 4);
+    assert_eq!(buffer.to_bytes(), vec![1, 0x0F, 3, 4]);
+}
+
+#[test]
+fn test_fixed_send_buffer() {
+    let mut send_buffer = vec![0xFFu8; 8];
+    {
+        let mut buffer = DataBuffer::fixed(&mut send_buffer[..]);
+        {
+            let mut section = buffer.begin_section(LengthKind::Smart);
+            section.write_u16(0xABCD);
+        }
+        assert_eq!(buffer.to_bytes(), vec![0x02, 0xAB, 0xCD]);
+
+        let section = buffer.try_begin_section(LengthKind::U32).unwrap();
+        assert!(section.close().is_ok());
+        assert!(buffer.try_begin_section(LengthKind::U16).is_err());
+        assert_eq!(buffer.len(), 7);
+    }
+    assert_eq!(send_buffer, [0x02, 0xAB, 0xCD, 0x00, 0x00, 0x00, 0x00, 0xFF]);
+}
+
+#[test]
+fn test_storage_backends() {
+    let bytes = [0x00, 0x2A, 0x07];
+    let mut borrowed = DataBuffer::with_storage(&bytes[..]);
+    assert_eq!(borrowed.read_u16(), 42);
+    assert_eq!(borrowed.peek_u8(), 7);
+    assert_eq!(borrowed.get_u8_at(0), 0);
+
+    let mut boxed = DataBuffer::with_storage(vec![0u8; 3].into_boxed_slice());
+    boxed.write_u16(0x0102);
+    assert_eq!(boxed.try_write_u16(3), Err(Error::CapacityExceeded { needed: 4, capacity: 3 }));
+    assert_eq!(&boxed.deconstruct()[..], &[0x01, 0x02, 0x00]);
+
+    let mut slice = [0xFFu8; 2];
+    {
+        let mut buffer = DataBuffer::with_storage(&mut slice[..]);
+        assert_eq!(buffer.read_u8(), 0xFF);
+        buffer.write_u8(0x10);
+        assert!(buffer.try_write_u16(0).is_err());
+    }
+    assert_eq!(slice, [0x10, 0xFF]);
+
+    let mut cow = DataBuffer::with_storage(Cow::Borrowed(&bytes[..]));
+    assert_eq!(cow.read_u8(), 0);
+    assert!(matches!(cow.storage(), Cow::Borrowed(_)));
+    cow.set_wpos(3);
+    cow.write_u8(8);
+    assert_eq!(cow.deconstruct().into_owned(), vec![0x00, 0x2A, 0x07, 0x08]);
+    assert_eq!(bytes, [0x00, 0x2A, 0x07]);
+}
+
+#[test]
+fn test_storage_clear_and_compact() {
+    let mut vec = DataBuffer::from_bytes(&[1, 2, 3]);
+    vec.read_u16();
+    vec.compact();
+    assert_eq!((vec.to_bytes(), vec.remaining()), (vec![3], 1));
+    vec.clear();
+    assert!(vec.is_empty());
+
+    // Storages with a fixed length cannot shrink, and are left untouched
+    let mut boxed = DataBuffer::with_storage(vec![1u8, 2, 3].into_boxed_slice());
+    boxed.read_u16();
+    assert_eq!(boxed.try_compact(), Err(Error::FixedLength { needed: 1, len: 3 }));
+    assert_eq!((boxed.to_bytes(), boxed.remaining()), (vec![1, 2, 3], 1));
+    assert_eq!(boxed.try_clear(), Err(Error::FixedLength { needed: 0, len: 3 }));
+    assert_eq!(boxed.read_u8(), 3);
+
+    let mut slice = [1u8, 2, 3, 4];
+    let mut borrowed = DataBuffer::with_storage(&mut slice[..]);
+    borrowed.read_u8();
+    assert_eq!(borrowed.try_compact(), Err(Error::FixedLength { needed: 3, len: 4 }));
+    assert_eq!(borrowed.try_clear(), Err(Error::FixedLength { needed: 0, len: 4 }));
+    assert_eq!((borrowed.len(), borrowed.read_u8()), (4, 2));
+
+    let bytes = [1, 2, 3];
+    let mut cow = DataBuffer::with_storage(Cow::Borrowed(&bytes[..]));
+    cow.read_u16();
+    cow.compact();
+    assert_eq!((cow.to_bytes(), cow.remaining()), (vec![3], 1));
+    cow.clear();
+    assert!(cow.is_empty());
+
+    let mut fixed = DataBuffer::fixed([0u8; 4]);
+    fixed.write_bytes(&[1, 2, 3]);
+    fixed.read_u16();
+    fixed.compact();
+    assert_eq!((fixed.to_bytes(), fixed.remaining()), (vec![3], 1));
+    fixed.write_u8(4);
+    fixed.clear();
+    assert!(fixed.is_empty());
+}
+
+#[test]
+fn test_clear_resets_bits_and_header() {
+    let mut buffer = DataBuffer::create(1, PacketHeader::SHORT);
+    buffer.write_bits(1, 3);
+    buffer.clear();
+    buffer.write_u8(0xAB);
+    buffer.finish();
+    assert_eq!(buffer.to_bytes(), vec![0xAB]);
+    assert_eq!(buffer.read_u8(), 0xAB);
+}
+
+#[test]
+fn test_pool() {
+    let pool = DataBufferPool::new(2).with_max_capacity(64);
+    {
+        let mut a = pool.get();
+        let mut b = pool.get();
+        let c = pool.get();
+        a.write_bytes(&[0; 16]);
+        a.write_bit(true);
+        b.write_bytes(&[0; 100]);
+        drop(c);
+    }
+    let stats = pool.stats();
+    assert_eq!((stats.hits(), stats.misses(), stats.pooled()), (0, 3, 2));
+    assert!(stats.bytes_retained() >= 17);
+
+    let mut reused = pool.create(3, PacketHeader::BYTE);
+    assert_eq!(pool.stats().hits(), 1);
+    assert_eq!(reused.to_bytes(), vec![0x03, 0xFF]);
+    reused.write_u8(9);
+    reused.finish();
+    assert_eq!(reused.to_bytes(), vec![0x03, 0x01, 0x09]);
+
+    let detached = reused.detach();
+    assert_eq!(detached.len(), 3);
+    assert_eq!(pool.stats().pooled(), 1);
+    pool.shrink();
+    assert_eq!(pool.stats().bytes_retained(), 0);
+}
+
+#[test]
+fn test_update_blocks_317() {
+    let mut blocks = UpdateBlocks::new();
+    blocks.add(Block::Hit { damage: 3, kind: 1, health: 7, max_health: 10 });
+    blocks.add(Block::Appearance(vec![0xAA, 0xBB]));
+    blocks.add(Block::Graphics { id: 0x0102, height: 100, delay: 0 });
+    blocks.add(Block::Chat { color: 1, effects: 2, rights: 0, text: vec![0x10, 0x20] });
+    blocks.add(Block::Hit { damage: 4, kind: 1, health: 6, max_health: 10 });
+    assert_eq!(blocks.mask(&Rs317Player).unwrap(), 0x1B0);
+
+    let mut buffer = DataBuffer::new();
+    buffer.write_update_blocks(&blocks, &Rs317Player);
+    assert_eq!(buffer.to_bytes(), vec![
+        0xF0, 0x01, // mask, with 0x40 set in the first byte
+        0x02, 0x01, 0x00, 0x64, 0x00, 0x00, // graphics
+        0x02, 0x01, 0x00, 0xFE, 0x20, 0x10, // chat, reversed
+        0xFE, 0xAA, 0xBB, // appearance
+        0x04, 0x81, 0xFA, 0x0A // hit
+    ]);
+
+    blocks.clear();
+    blocks.add(Block::ForcedMovement { start_x: 1, start_y: 2, end_x: 3, end_y: 4, start_tick: 5, end_tick: 6, direction: 0 });
+    blocks.add(Block::ForcedChat("Hi".to_owned()));
+    let mut buffer = DataBuffer::new();
+    buffer.write_update_blocks(&blocks, &Rs317Player);
+    assert_eq!(buffer.to_bytes(), vec![
+        0x44, 0x04, 0x7F, 0x7E, 0x7D, 0x7C, 0x85, 0x00, 0x00, 0x86, 0x80, 0x48, 0x69, 0x0A
+    ]);
+
+    blocks.add(Block::ForcedChat("a\nb".to_owned()));
+    let mut buffer = DataBuffer::new();
+    assert_eq!(buffer.try_write_update_blocks(&blocks, &Rs317Player), Err(Error::UnmappableChar { ch: '\n' }));
+    assert!(buffer.is_empty());
+
+    blocks.clear();
+    blocks.add(Block::FaceCoordinate { x: 3222, y: 3218 });
+    buffer.write_update_blocks(&blocks, &Rs317Player);
+    assert_eq!(buffer.to_bytes(), vec![0x02, 0xAD, 0x19, 0x25, 0x19]);
+
+    blocks.add(Block::FaceCoordinate { x: 3222, y: 32768 });
+    let mut buffer = DataBuffer::new();
+    assert_eq!(buffer.try_write_update_blocks(&blocks, &Rs317Player),
+        Err(Error::ValueOutOfRange { value: 32768, max: 0x7FFF }));
+    assert!(buffer.is_empty());
+}
+
+struct ThreeByteRevision;
+
+impl Revision for ThreeByteRevision {
+    fn table(&self) -> &[(BlockKind, u32)] {
+        &[(BlockKind::FaceEntity, 0x10000), (BlockKind::Animation, 0x1)]
+    }
+
+    fn extension_flag(&self) -> u8 {
+        0x80
+    }
+
+    fn write_block<S: StorageMut>(&self, buffer: &mut DataBuffer<S>, block: &Block) -> Result<()> {
+        match *block {
+            Block::FaceEntity(index) => buffer.try_write_u16(index),
+            Block::Animation { id, delay } => {
+                buffer.try_write_u16(id)?;
+                buffer.try_write_u8(delay)
+            }
+            _ => unreachable!()
+        }
+    }
+}
+
+#[test]
+fn test_update_blocks_custom_revision() {
+    let mut blocks = UpdateBlocks::new();
+    blocks.add(Block::Animation { id: 7, delay: 1 });
+    blocks.add(Block::FaceEntity(9));
+    let mut buffer = DataBuffer::new();
+    buffer.write_update_blocks(&blocks, &ThreeByteRevision);
+    assert_eq!(buffer.to_bytes(), vec![0x81, 0x80, 0x01, 0x00, 0x09, 0x00, 0x07, 0x01]);
+
+    assert_eq!(blocks.remove(BlockKind::FaceEntity), Some(Block::FaceEntity(9)));
+    assert!(!blocks.contains(BlockKind::FaceEntity));
+    blocks.add(Block::Hit { damage: 1, kind: 0, health: 1, max_health: 1 });
+    assert_eq!(blocks.mask(&ThreeByteRevision), Err(Error::Unsupported { what: "the hit block" }));
+    assert!(DataBuffer::new().try_write_update_mask(0x1000000, 0x80).is_err());
+}
+
+#[test]
+fn test_local_movement() {
+    let mut buffer = DataBuffer::new();
+    buffer.write_local_movement(Movement::Stay, false);
+    buffer.write_local_movement(Movement::Stay, true);
+    buffer.write_local_movement(Movement::Walk(6), false);
+    buffer.write_local_movement(Movement::Run(1, 2), true);
+    buffer.write_local_movement(Movement::Teleport { plane: 2, x: 50, y: 60, discard_queue: true }, true);
+    assert_eq!(buffer.read_bits(1), 0);
+    assert_eq!(buffer.read_bits(3), 0b100);
+    assert_eq!(buffer.read_bits(1), 1);
+    assert_eq!(buffer.read_bits(2), 1);
+    assert_eq!(buffer.read_bits(3), 6);
+    assert_eq!(buffer.read_bits(1), 0);
+    assert_eq!(buffer.read_bits(1), 1);
+    assert_eq!(buffer.read_bits(2), 2);
+    assert_eq!(buffer.read_bits(3), 1);
+    assert_eq!(buffer.read_bits(3), 2);
+    assert_eq!(buffer.read_bits(1), 1);
+    assert_eq!(buffer.read_bits(3), 0b1_11);
+    assert_eq!(buffer.read_bits(2), 2);
+    assert!(buffer.read_bit());
+    assert!(buffer.read_bit());
+    assert_eq!(buffer.read_bits(7), 60);
+    assert_eq!(buffer.read_bits(7), 50);
+
+    let len = buffer.len();
+    assert!(buffer.try_write_local_movement(Movement::Walk(8), false).is_err());
+    assert!(buffer.try_write_local_movement(Movement::Teleport { plane: 4, x: 0, y: 0, discard_queue: false }, false).is_err());
+    assert_eq!(buffer.len(), len);
+    assert_eq!(movement::direction(0, -1), Some(6));
+    assert_eq!(movement::direction(1, 1), Some(2));
+}
+
+#[test]
+fn test_entity_list_players() {
+    let viewer = (3200, 3200);
+    let current = [
+        LocalEntity::new(5, 3201, 3200),
+        LocalEntity::new(1, 3202, 3201).with_movement(Movement::Walk(4)).with_update(true),
+        LocalEntity::new(8, 3190, 3215).with_movement(Movement::Teleport { plane: 0, x: 0, y: 0, discard_queue: true }),
+        LocalEntity::new(2047 - 1, 3185, 3215).with_update(true)
+    ];
+    let mut buffer = DataBuffer::new();
+    let list = buffer.write_entity_list(ListFormat::players(), viewer, &[1, 3, 5, 8], &current);
+    assert_eq!(list, vec![1, 5, 2046]);
+
+    assert_eq!(buffer.read_bits(8), 4);
+    // 1 walked east with update blocks, 3 is gone, 5 stayed, 8 teleported
+    assert_eq!(buffer.read_bits(1), 1);
+    assert_eq!(buffer.read_bits(2), 1);
+    assert_eq!(buffer.read_bits(3), 4);
+    assert_eq!(buffer.read_bits(1), 1);
+    assert_eq!(buffer.read_bits(3), 0b1_11);
+    assert_eq!(buffer.read_bits(1), 0);
+    assert_eq!(buffer.read_bits(3), 0b1_11);
+    // 2046 is added, with update blocks and deltas of (-15, 15)
+    assert_eq!(buffer.read_bits(11), 2046);
+    assert!(buffer.read_bit());
+    assert!(buffer.read_bit());
+    assert_eq!(buffer.read_bits_signed(5), 15);
+    assert_eq!(buffer.read_bits_signed(5), -15);
+    assert_eq!(buffer.read_bits(11), 2047);
+    assert_eq!(buffer.len(), 7);
+
+    let next = [LocalEntity::new(5, 3201, 3200), LocalEntity::new(8, 3210, 3200)];
+    let mut buffer = DataBuffer::new();
+    assert_eq!(buffer.write_entity_list(ListFormat::players(), viewer, &list, &next), vec![5, 8]);
+    assert_eq!(buffer.read_bits(8), 3);
+    assert_eq!(buffer.read_bits(3), 0b1_11);
+    assert_eq!(buffer.read_bits(1), 0);
+    assert_eq!(buffer.read_bits(3), 0b1_11);
+    assert_eq!(buffer.read_bits(11), 8);
+}
+
+#[test]
+fn test_entity_list_npcs() {
+    let viewer = (3200, 3200);
+    let current = [
+        LocalEntity::new(300, 3199, 3198).with_movement(Movement::Run(5, 6)),
+        LocalEntity::new(20000, 3196, 3201).with_id(1265).with_update(true)
+    ];
+    let mut buffer = DataBuffer::new();
+    assert_eq!(buffer.write_entity_list(ListFormat::npcs(), viewer, &[300], &current), vec![300, 20000]);
+    assert_eq!(buffer.read_bits(8), 1);
+    assert_eq!(buffer.read_bits(1), 1);
+    assert_eq!(buffer.read_bits(2), 2);
+    assert_eq!(buffer.read_bits(3), 5);
+    assert_eq!(buffer.read_bits(3), 6);
+    assert_eq!(buffer.read_bits(1), 0);
+    assert_eq!(buffer.read_bits(15), 20000);
+    assert_eq!(buffer.read_bits_signed(5), 1);
+    assert_eq!(buffer.read_bits_signed(5), -4);
+    assert!(buffer.read_bit());
+    assert_eq!(buffer.read_bits(14), 1265);
+    assert!(buffer.read_bit());
+    assert_eq!(buffer.read_bits(15), 32767);
+
+    let format = ListFormat::npcs().with_index_bits(14).with_id_bits(12);
+    assert_eq!(format.terminator(), 16383);
+    let mut buffer = DataBuffer::new();
+    buffer.write_u8(0xFF);
+    assert!(buffer.try_write_entity_list(format, viewer, &[], &current).is_err());
+    let far = [LocalEntity::new(1, 3216, 3200)];
+    assert!(buffer.try_write_entity_list(format, viewer, &[], &far).is_err());
+    let too_many: Vec<u16> = (0..256).collect();
+    assert!(buffer.try_write_entity_list(format, viewer, &too_many, &[]).is_err());
+    assert_eq!(buffer.to_bytes(), vec![0xFF]);
+}
+
+#[test]
+fn test_list_format_widths() {
+    assert_eq!(ListFormat::players().with_index_bits(1).terminator(), 1);
+    assert_eq!(ListFormat::players().with_index_bits(32).terminator(), 0xFFFF_FFFF);
+}
+
+#[test]
+#[should_panic]
+fn test_list_format_zero_width() {
+    ListFormat::players().with_index_bits(0);
+}
+
+#[test]
+#[should_panic]
+fn test_list_format_wide_types() {
+    ListFormat::npcs().with_id_bits(64);
+}
+
+#[test]
+fn test_coordinate() {
+    let coord = Coordinate::new(3093, 3493, 1);
+    assert_eq!(coord.packed(), 1 << 28 | 3093 << 14 | 3493);
+    assert_eq!(Coordinate::from_packed(coord.packed()), coord);
+    assert_eq!((coord.region_x(), coord.region_y()), (48, 54));
+    assert_eq!(coord.region_id(), 12342);
+    assert_eq!((coord.chunk_x(), coord.chunk_y()), (386, 436));
+    assert_eq!(coord.chunk_hash(), 1 << 24 | 386 << 12 | 436);
+    assert_eq!(coord.region_offset(), (21, 37));
+
+    let base = coord.region_base();
+    assert_eq!(base, Coordinate::new(3040, 3440, 1));
+    assert_eq!(coord.local_to(base), Some((53, 53)));
+    assert_eq!(Coordinate::new(3039, 3440, 1).local_to(base), None);
+    assert_eq!(Coordinate::new(3144, 3440, 1).local_to(base), None);
+    assert_eq!(Coordinate::new(20, 20, 0).region_base(), Coordinate::new(0, 0, 0));
+}
+
+#[test]
+fn test_coord_packed() {
+    let coord = Coordinate::new(3222, 3218, 2);
+    let mut buffer = DataBuffer::new();
+    buffer.write_coord_packed(coord);
+    buffer.write_center_chunk(coord, DataBuffer::try_write_u16, DataBuffer::try_write_u16_le);
+    assert_eq!(buffer.to_bytes(), vec![0x23, 0x25, 0x8C, 0x92, 0x01, 0x92, 0x92, 0x01]);
+    assert_eq!(buffer.read_coord_packed(), coord);
+    assert_eq!(buffer.read_center_chunk(DataBuffer::try_read_u16, DataBuffer::try_read_u16_le),
+        Coordinate::new(3216, 3216, 0));
+
+    assert!(buffer.try_write_coord_packed(Coordinate::new(0x4000, 0, 0)).is_err());
+    assert!(buffer.try_write_coord_packed(Coordinate::new(0, 0, 4)).is_err());
+    assert_eq!(buffer.len(), 8);
+
+    let mut buffer = DataBuffer::from_bytes(&[0x08, 0x00, 0x00, 0x00]);
+    assert!(buffer.try_read_center_chunk(DataBuffer::try_read_u16, DataBuffer::try_read_u16).is_err());
+    assert_eq!(buffer.remaining(), 4);
+}
+
+#[test]
+fn test_palette() {
+    let mut palette = Palette::new();
+    let boss_room = PaletteChunk::new(Coordinate::new(2275, 4683, 0), 0);
+    let corridor = PaletteChunk::new(Coordinate::new(3200, 3200, 1), 3);
+    assert_eq!(boss_room.coord(), Coordinate::new(2272, 4680, 0));
+    assert_eq!(boss_room.packed().unwrap(), 284 << 14 | 585 << 3);
+    assert_eq!(PaletteChunk::from_packed(corridor.packed().unwrap()), corridor);
+
+    assert_eq!(palette.set(1, 0, 1, corridor), None);
+    palette.set(0, 12, 12, boss_room);
+    palette.set(2, 3, 4, boss_room);
+    palette.set_keys(boss_room.coord().region_id(), [-1, 2, -3, 4]);
+    assert_eq!(palette.regions(), vec![boss_room.coord().region_id(), corridor.coord().region_id()]);
+    assert_eq!(palette.get(1, 0, 1), Some(&corridor));
+    assert_eq!(palette.keys(corridor.coord().region_id()), [0; 4]);
+
+    let mut buffer = DataBuffer::new();
+    buffer.write_palette(&palette);
+    assert_eq!(buffer.len(), (4 * 13 * 13 + 3 * 26usize).div_ceil(8) + 2 * 16);
+    assert_eq!(buffer.get_bits_at(0, 8), 0);
+    assert!(buffer.get_bit(168));
+    assert_eq!(buffer.get_bits_at(169, 26), boss_room.packed().unwrap() as u64);
+    assert_eq!(buffer.read_palette(), palette);
+
+    let mut bytes = buffer.to_bytes();
+    bytes.pop();
+    let mut buffer = DataBuffer::from_bytes(&bytes);
+    assert!(buffer.try_read_palette().is_err());
+    assert_eq!(buffer.remaining(), bytes.len());
+
+    assert_eq!(palette.remove(1, 0, 1), Some(corridor));
+    palette.set(3, 0, 0, PaletteChunk::new(Coordinate::new(0, 0, 0), 4));
+    let mut buffer = DataBuffer::new();
+    assert!(buffer.try_write_palette(&palette).is_err());
+    assert!(buffer.is_empty());
+}
+
+#[cfg(feature = "serde")]
+mod serde_tests {
+    use databuffer::*;
+    use databuffer::serialization::*;
+    use serde::{Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Action {
+        Idle,
+        Walk { x: u16, y: u16 },
+        Say(String)
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Player {
+        id: u16,
+        name: String,
+        member: bool,
+        skills: Vec<u8>,
+        action: Action,
+        clan: Option<String>
+    }
+
+    fn player() -> Player {
+        Player {
+            id: 200,
+            name: "Zezima".to_owned(),
+            member: true,
+            skills: vec![99, 1],
+            action: Action::Walk { x: 3222, y: 3218 },
+            clan: None
+        }
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let configs = [
+            Config::new(),
+            Config::new().with_ints(IntEncoding::LittleEndian).with_lengths(LengthKind::U8),
+            Config::new().with_ints(IntEncoding::VarInt).with_lengths(LengthKind::VarInt)
+                .with_strings(StringEncoding::Utf8(LengthKind::VarInt)),
+            Config::new().with_ints(IntEncoding::Smart).with_lengths(LengthKind::Smart)
+                .with_strings(StringEncoding::Cp1252Terminated),
+            Config::new().with_strings(StringEncoding::JavaUtf),
+        ];
+        for config in configs.iter().cloned() {
+            let mut buffer = DataBuffer::new();
+            buffer.write_serde(&player(), config);
+            assert_eq!(buffer.read_serde::<Player>(config), player());
+            assert_eq!(buffer.remaining(), 0);
+        }
+    }
+
+    #[test]
+    fn test_serde_layout() {
+        let config = Config::new().with_ints(IntEncoding::Smart).with_lengths(LengthKind::U8)
+            .with_strings(StringEncoding::Cp1252Terminated);
+        let bytes = to_buffer(&player(), config).unwrap().to_bytes();
+        assert_eq!(bytes, vec![
+            0x80, 0xC8, // id as a smart
+            0x5A, 0x65, 0x7A, 0x69, 0x6D, 0x61, 0x00, // name
+            0x01, // member
+            0x02, 0x63, 0x01, // skills
+            0x01, 0x8C, 0x96, 0x8C, 0x92, // action, variant 1 with smart fields
+            0x00 // clan
+        ]);
+        assert_eq!(from_bytes::<Player>(&bytes, config), Ok(player()));
+    }
+
+    #[test]
+    fn test_serde_unknown_length() {
+        let mut map = BTreeMap::new();
+        map.insert(1u8, Action::Idle);
+        map.insert(2u8, Action::Say("hi".to_owned()));
+
+        let config = Config::new().with_lengths(LengthKind::VarInt);
+        let mut buffer = DataBuffer::new();
+        buffer.write_serde(&map, config);
+        assert_eq!(buffer.to_bytes(), vec![0x02, 0x01, 0x00, 0x02, 0x02, 0x00, 0x00, 0x00, 0x02, 0x68, 0x69]);
+        assert_eq!(buffer.read_serde::<BTreeMap<u8, Action>>(config), map);
+    }
+
+    /// Serializes a sequence and a map from iterators without a size hint
+    struct Unsized(Vec<u16>, BTreeMap<u8, Action>);
+
+    impl Serialize for Unsized {
+        fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            use serde::ser::SerializeTuple;
+            let mut tuple = serializer.serialize_tuple(2)?;
+            tuple.serialize_element(&Seq(&self.0))?;
+            tuple.serialize_element(&Map(&self.1))?;
+            tuple.end()
+        }
+    }
+
+    struct Seq<'a>(&'a [u16]);
+
+    impl Serialize for Seq<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.0.iter().filter(|_| true))
+        }
+    }
+
+    struct Map<'a>(&'a BTreeMap<u8, Action>);
+
+    impl Serialize for Map<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            serializer.collect_map(self.0.iter().filter(|_| true))
+        }
+    }
+
+    #[test]
+    fn test_serde_unknown_count() {
+        let mut map = BTreeMap::new();
+        map.insert(1u8, Action::Idle);
+        map.insert(2u8, Action::Say("hi".to_owned()));
+        let value = Unsized(vec![300, 400, 500], map);
+
+        for &lengths in &[LengthKind::U8, LengthKind::U16, LengthKind::Smart, LengthKind::VarInt] {
+            let config = Config::new().with_lengths(lengths);
+            let mut buffer = DataBuffer::new();
+            buffer.write_serde(&value, config);
+            let (seq, map) = buffer.read_serde::<(Vec<u16>, BTreeMap<u8, Action>)>(config);
+            assert_eq!(seq, value.0);
+            assert_eq!(map, value.1);
+            assert_eq!(buffer.remaining(), 0);
+        }
+
+        let bytes = to_buffer(&Seq(&[1, 2]), Config::new().with_lengths(LengthKind::U8)).unwrap().to_bytes();
+        assert_eq!(bytes, vec![0x02, 0x00, 0x01, 0x00, 0x02]);
+    }
+
+    #[test]
+    fn test_serde_errors() {
+        let config = Config::new().with_lengths(LengthKind::U8).with_max_len(2);
+        let mut buffer = DataBuffer::from_bytes(&[0x03, 0x01, 0x02, 0x03]);
+        assert_eq!(buffer.try_read_serde::<Vec<u8>>(config), Err(Error::LengthLimitExceeded { length: 3, max: 2 }));
+        assert_eq!(buffer.get_rpos(), 0);
+
+        let mut buffer = DataBuffer::from_bytes(&[0x02]);
+        assert!(buffer.try_read_serde::<bool>(config).is_err());
+        assert!(buffer.try_read_serde::<Option<u8>>(config).is_err());
+
+        let mut buffer = DataBuffer::new();
+        buffer.write_u8(0xFF);
+        let config = Config::new().with_ints(IntEncoding::Smart);
+        assert_eq!(buffer.try_write_serde(&(1u8, 0x8000u16), config),
+            Err(Error::ValueOutOfRange { value: 0x8000, max: 0x7FFF }));
+        assert_eq!(buffer.to_bytes(), vec![0xFF]);
+        assert_eq!(buffer.get_wpos(), 1);
+    }
+}